@@ -0,0 +1,48 @@
+use actix_web::http::header::{self, HeaderValue};
+use actix_web::http::{Method, StatusCode};
+use actix_web::{HttpRequest, HttpResponse, Resource, web};
+
+use crate::convert::MIME_TYPE;
+use crate::encoding::ALLOWED_METHODS;
+use crate::exporter::OpenMetricsExporter;
+
+/// Creates an actix-web [Resource] at `path` which serves the last-exported text of `exporter`.
+///
+/// The text is compressed if the request accepts it, the same as with [tower](crate::tower).
+///
+/// ```ignore
+/// App::new().service(opentelemetry_openmetrics::actix::resource("/metrics", exporter.clone()))
+/// ```
+pub fn resource(path: &str, exporter: OpenMetricsExporter) -> Resource {
+    web::resource(path)
+        .app_data(web::Data::new(exporter))
+        .to(handler)
+}
+
+/// An actix-web handler which serves the last-exported text of the [OpenMetricsExporter] in the app data.
+pub async fn handler(
+    exporter: web::Data<OpenMetricsExporter>,
+    request: HttpRequest,
+) -> HttpResponse {
+    if request.method() != Method::GET && request.method() != Method::HEAD {
+        return HttpResponse::build(StatusCode::METHOD_NOT_ALLOWED)
+            .insert_header((header::ALLOW, ALLOWED_METHODS))
+            .finish();
+    }
+
+    let (encoding, body) = exporter.encoded_snapshot(
+        request
+            .headers()
+            .get_all(header::ACCEPT_ENCODING)
+            .filter_map(|value| value.to_str().ok()),
+    );
+
+    let mut response = HttpResponse::Ok();
+    response
+        .insert_header((header::CONTENT_TYPE, HeaderValue::from_static(MIME_TYPE)))
+        .insert_header((header::VARY, "accept-encoding"));
+    if let Some(content_encoding) = encoding.header_value() {
+        response.insert_header((header::CONTENT_ENCODING, content_encoding));
+    }
+    response.body(body)
+}
@@ -1,18 +1,40 @@
 use std::borrow::Cow;
+use std::collections::{BTreeSet, HashMap};
 use std::fmt::Write;
 use std::hash::{DefaultHasher, Hasher};
+use std::io;
+use std::ops::Bound;
 use std::time::SystemTime;
 
 use crate::format::FastDisplay;
 use opentelemetry::{Key, KeyValue, Value};
 use opentelemetry_sdk::metrics::Temporality;
 use opentelemetry_sdk::metrics::data::{
-    AggregatedMetrics, Gauge, Histogram, MetricData, ResourceMetrics, Sum,
+    AggregatedMetrics, ExponentialBucket, ExponentialHistogram, Gauge, Histogram, MetricData,
+    ResourceMetrics, Sum,
 };
 use opentelemetry_sdk::metrics::data::{Metric, ScopeMetrics};
-use ufmt::{uDisplay, uWrite, uwriteln};
+use ufmt::{uDisplay, uWrite, uwrite, uwriteln};
 use unit::get_unit_suffixes;
 
+pub use accumulator::DeltaAccumulator;
+use accumulator::{Accumulate, IdleMetric, MetricKind, NumberKind, SeriesValues};
+pub use encoder::Encoder;
+#[cfg(feature = "tokio")]
+pub use encoder::write_as_openmetrics_async;
+pub use error::ConversionError;
+use exemplar::{ExemplarData, ExemplarValue, collect_exemplars, latest_exemplar, write_exemplar};
+pub use negotiate::{ExpositionFormat, negotiate};
+pub use options::{ConversionOptions, TimestampMode, TranslationStrategy};
+pub use report::{ConversionReport, DroppedMetric, DroppedUnit, RenamedMetric, SanitizedLabel};
+
+mod accumulator;
+mod encoder;
+mod error;
+mod exemplar;
+mod negotiate;
+mod options;
+mod report;
 #[cfg(test)]
 mod tests;
 mod unit;
@@ -20,43 +42,131 @@ mod unit;
 /// The mime type of the text produced by this metrics formatter.
 pub const MIME_TYPE: &str = "application/openmetrics-text; version=1.0.0; charset=utf-8";
 
+/// The mime type of the text produced by [WritePrometheus].
+pub const PROMETHEUS_MIME_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
 /// Trait to write the metrics data in OpenMetrics text format.
 pub trait WriteOpenMetrics {
     /// Writes the metrics into `f` in OpenMetrics text format.
-    fn write_as_openmetrics(&self, f: &mut impl Write) -> std::fmt::Result;
+    fn write_as_openmetrics(&self, f: &mut impl Write) -> Result<(), ConversionError> {
+        self.write_as_openmetrics_with(&ConversionOptions::default(), f)
+    }
+    /// Writes the metrics into `f` in OpenMetrics text format, as configured by `options`.
+    fn write_as_openmetrics_with(
+        &self,
+        options: &ConversionOptions,
+        f: &mut impl Write,
+    ) -> Result<(), ConversionError>;
+    /// Writes the metrics into `f` in OpenMetrics text format, as configured by `options`, and returns a report of
+    /// the metrics which were skipped or altered.
+    fn write_as_openmetrics_with_report(
+        &self,
+        options: &ConversionOptions,
+        f: &mut impl Write,
+    ) -> Result<ConversionReport, ConversionError>;
+    /// Writes the metrics into the byte sink `w` in OpenMetrics text format.
+    ///
+    /// The text is written in many small pieces, so `w` should be buffered, e.g. by a [std::io::BufWriter].
+    /// Errors of `w` are returned as [ConversionError::Io].
+    fn write_as_openmetrics_io(&self, w: &mut impl io::Write) -> Result<(), ConversionError> {
+        self.write_as_openmetrics_io_with(&ConversionOptions::default(), w)
+    }
+    /// Writes the metrics into the byte sink `w` in OpenMetrics text format, as configured by `options`.
+    ///
+    /// See [write_as_openmetrics_io](Self::write_as_openmetrics_io).
+    fn write_as_openmetrics_io_with(
+        &self,
+        options: &ConversionOptions,
+        w: &mut impl io::Write,
+    ) -> Result<(), ConversionError> {
+        let mut adapter = IoAsWrite { w, error: None };
+        self.write_as_openmetrics_with(options, &mut adapter)
+            .map_err(|err| adapter.error.map_or(err, ConversionError::Io))
+    }
     /// Creates and returns a [String] of the metrics data in OpenMetrics text format.
-    fn to_openmetrics_string(&self) -> Result<String, std::fmt::Error> {
+    fn to_openmetrics_string(&self) -> Result<String, ConversionError> {
         let mut out = String::new();
         self.write_as_openmetrics(&mut out)?;
         Ok(out)
     }
 }
 
+/// Trait to write the metrics data in the Prometheus text exposition format 0.0.4.
+///
+/// Compared to OpenMetrics, there is no `# UNIT` metadata, no `_created` samples and no `# EOF` marker.
+/// Counter families include the `_total` suffix in their name and timestamps are written in integer milliseconds,
+/// see [TimestampMode].
+pub trait WritePrometheus {
+    /// Writes the metrics into `f` in Prometheus text format.
+    fn write_as_prometheus(&self, f: &mut impl Write) -> Result<(), ConversionError> {
+        self.write_as_prometheus_with(&ConversionOptions::default(), f)
+    }
+    /// Writes the metrics into `f` in Prometheus text format, as configured by `options`.
+    fn write_as_prometheus_with(
+        &self,
+        options: &ConversionOptions,
+        f: &mut impl Write,
+    ) -> Result<(), ConversionError>;
+    /// Creates and returns a [String] of the metrics data in Prometheus text format.
+    fn to_prometheus_string(&self) -> Result<String, ConversionError> {
+        let mut out = String::new();
+        self.write_as_prometheus(&mut out)?;
+        Ok(out)
+    }
+}
+
+/// The text exposition formats supported by the converter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Format {
+    /// [OpenMetrics 1.0](https://github.com/prometheus/OpenMetrics/blob/v1.0.0/specification/OpenMetrics.md)
+    OpenMetrics,
+    /// [Prometheus 0.0.4](https://prometheus.io/docs/instrumenting/exposition_formats/#text-based-format)
+    Prometheus,
+}
+
 /// Serialization context for common variables needed during conversion.
 struct Context<'f, W: uWrite> {
     /// the output [Write] reference
     f: W,
     /// a temporary buffer to store the serialized metric attributes
     attr_buffer: String,
-    /// the sanitized name of the current metric
+    /// the translated name of the current metric
     name: String,
+    /// whether the name of the current metric is written in the quoted syntax
+    quoted_name: bool,
     /// the converted unit string of the current metric
     unit: Option<Cow<'static, str>>,
     /// the OpenMetrics metric type of the current metric
     typ: &'static str,
     /// the name of the current scope
     scope_name: &'f str,
+    /// whether the samples of the current family are labeled with the scope name
+    scope_label: bool,
+    /// the options of this conversion, possibly adjusted by content negotiation
+    options: Cow<'f, ConversionOptions>,
+    /// the running totals of delta metrics, if delta temporality is supported
+    accumulator: Option<&'f mut DeltaAccumulator>,
+    /// the report of skipped and altered metrics, if requested
+    report: Option<&'f mut ConversionReport>,
+    /// the format to write
+    format: Format,
 }
 
 impl<'f, W: Write> Context<'f, WriteAsUWrite<'f, W>> {
-    fn with_output(f: &'f mut W) -> Self {
+    fn with_output(f: &'f mut W, options: &'f ConversionOptions) -> Self {
         Context {
             f: WriteAsUWrite(f),
             attr_buffer: String::with_capacity(256),
             name: String::with_capacity(64),
+            quoted_name: false,
             unit: None,
             typ: "",
             scope_name: "",
+            scope_label: options.scope_info,
+            options: Cow::Borrowed(options),
+            accumulator: None,
+            report: None,
+            format: Format::OpenMetrics,
         }
     }
 }
@@ -75,79 +185,484 @@ impl<'w, W: Write> uWrite for WriteAsUWrite<'w, W> {
     }
 }
 
+/// Adapter to write into an [io::Write] through [Write], keeping the original error.
+struct IoAsWrite<'w, W: io::Write> {
+    w: &'w mut W,
+    error: Option<io::Error>,
+}
+
+impl<'w, W: io::Write> Write for IoAsWrite<'w, W> {
+    fn write_str(&mut self, s: &str) -> std::fmt::Result {
+        self.w.write_all(s.as_bytes()).map_err(|err| {
+            self.error = Some(err);
+            std::fmt::Error
+        })
+    }
+}
+
 impl WriteOpenMetrics for ResourceMetrics {
-    fn write_as_openmetrics(&self, f: &mut impl Write) -> std::fmt::Result {
-        let mut ctx = Context::with_output(f);
+    fn write_as_openmetrics_with(
+        &self,
+        options: &ConversionOptions,
+        f: &mut impl Write,
+    ) -> Result<(), ConversionError> {
+        write_resource_metrics(self, options, Format::OpenMetrics, None, None, f)
+    }
 
-        #[cfg(feature = "otel_scope_info")]
-        write_target_info(&mut ctx.f, self.resource())?;
+    fn write_as_openmetrics_with_report(
+        &self,
+        options: &ConversionOptions,
+        f: &mut impl Write,
+    ) -> Result<ConversionReport, ConversionError> {
+        let mut report = ConversionReport::default();
+        write_resource_metrics(
+            self,
+            options,
+            Format::OpenMetrics,
+            None,
+            Some(&mut report),
+            f,
+        )?;
+        Ok(report)
+    }
+}
 
-        let mut scopes: Vec<&ScopeMetrics> = self.scope_metrics().collect();
-        scopes.sort_unstable_by_key(|s| s.scope().name());
+impl WritePrometheus for ResourceMetrics {
+    fn write_as_prometheus_with(
+        &self,
+        options: &ConversionOptions,
+        f: &mut impl Write,
+    ) -> Result<(), ConversionError> {
+        write_resource_metrics(self, options, Format::Prometheus, None, None, f)
+    }
+}
 
-        #[cfg(feature = "otel_scope_info")]
-        write_otel_scope_info(&mut ctx.f, &scopes)?;
+/// Write `metrics` into `f` in `format`. Metrics with delta temporality are only supported if an `accumulator` is
+/// given. Skipped and altered metrics are recorded in `report`, if given.
+fn write_resource_metrics(
+    metrics: &ResourceMetrics,
+    options: &ConversionOptions,
+    format: Format,
+    accumulator: Option<&mut DeltaAccumulator>,
+    report: Option<&mut ConversionReport>,
+    f: &mut impl Write,
+) -> Result<(), ConversionError> {
+    let idle = accumulator
+        .as_deref()
+        .map(|accumulator| accumulator.idle_metrics(metrics))
+        .unwrap_or_default();
+    let mut ctx = Context {
+        accumulator,
+        report,
+        format,
+        ..Context::with_output(f, options)
+    };
 
-        for scope in scopes {
-            if cfg!(feature = "otel_scope_info") {
-                ctx.scope_name = scope.scope().name();
+    let scopes = sorted_scopes(metrics);
+    write_info_metrics(&mut ctx, metrics, &scopes)?;
+    for family in group_families(&mut ctx, &scopes, &idle)? {
+        write_family(&mut ctx, &family)?;
+    }
+    write_eof(&mut ctx)
+}
+
+/// Get the scopes of `metrics` in the order they are written.
+fn sorted_scopes(metrics: &ResourceMetrics) -> Vec<&ScopeMetrics> {
+    let mut scopes: Vec<&ScopeMetrics> = metrics.scope_metrics().collect();
+    scopes.sort_unstable_by_key(|s| s.scope().name());
+    scopes
+}
+
+/// Get the metrics of `scope` in the order they are written.
+fn sorted_metrics(scope: &ScopeMetrics) -> Vec<&Metric> {
+    let mut metrics: Vec<_> = scope.metrics().collect();
+    metrics.sort_unstable_by_key(|met| met.name());
+    metrics
+}
+
+/// Write the `target_info` and `otel_scope_info` metrics, if enabled.
+fn write_info_metrics<U: uWrite<Error = std::fmt::Error>>(
+    ctx: &mut Context<'_, U>,
+    metrics: &ResourceMetrics,
+    scopes: &Vec<&ScopeMetrics>,
+) -> Result<(), ConversionError> {
+    if ctx.options.target_info {
+        write_target_info(&mut ctx.f, &ctx.options, ctx.format, metrics.resource())?;
+    }
+    if ctx.options.scope_info {
+        write_otel_scope_info(&mut ctx.f, &ctx.options, ctx.format, scopes)?;
+    }
+    Ok(())
+}
+
+/// A metric family: the metrics of all scopes which are written under the same name.
+struct Family<'a> {
+    /// the translated name, see [Context::name]
+    name: String,
+    /// whether the name is written in the quoted syntax
+    quoted_name: bool,
+    /// the converted unit string
+    unit: Option<Cow<'static, str>>,
+    /// the OpenMetrics metric type
+    typ: &'static str,
+    /// the metrics and the names of their scopes, in the order they are written
+    metrics: Vec<(&'a str, FamilyMember<'a>)>,
+}
+
+/// A metric of a [Family].
+#[derive(Clone, Copy)]
+enum FamilyMember<'a> {
+    /// a metric of the current conversion
+    Metric(&'a Metric),
+    /// an accumulated metric without data points in the current conversion, see [DeltaAccumulator]
+    Idle(&'a IdleMetric),
+}
+
+impl FamilyMember<'_> {
+    fn name(&self) -> &str {
+        match self {
+            FamilyMember::Metric(metric) => metric.name(),
+            FamilyMember::Idle(idle) => &idle.metric.name,
+        }
+    }
+
+    fn description(&self) -> &str {
+        match self {
+            FamilyMember::Metric(metric) => metric.description(),
+            FamilyMember::Idle(idle) => &idle.metric.description,
+        }
+    }
+}
+
+/// Groups the metrics of `scopes` and the `idle` accumulated metrics by their translated name, so that every family is
+/// written with a single header. The families are ordered by their first metric.
+///
+/// Metrics which cannot be converted, whose type or unit conflict with an earlier metric of the same family, or whose
+/// scope already has a metric in the same family, are skipped in lenient mode.
+fn group_families<'a, U: uWrite>(
+    ctx: &mut Context<'a, U>,
+    scopes: &[&'a ScopeMetrics],
+    idle: &'a [IdleMetric],
+) -> Result<Vec<Family<'a>>, ConversionError> {
+    let mut members: Vec<(&'a str, FamilyMember<'a>)> = Vec::new();
+    for scope in scopes {
+        let metrics = sorted_metrics(scope).into_iter();
+        members.extend(metrics.map(|metric| (scope.scope().name(), FamilyMember::Metric(metric))));
+    }
+    if !idle.is_empty() {
+        members.extend(
+            idle.iter()
+                .map(|idle| (idle.metric.scope.as_str(), FamilyMember::Idle(idle))),
+        );
+        members.sort_by(|a, b| (a.0, a.1.name()).cmp(&(b.0, b.1.name())));
+    }
+
+    let mut families: Vec<Family<'a>> = Vec::new();
+    let mut indices: HashMap<String, usize> = HashMap::new();
+    for (scope_name, member) in members {
+        ctx.scope_name = scope_name;
+        let extracted = match member {
+            FamilyMember::Metric(metric) => extract_type_unit_and_name(ctx, metric),
+            FamilyMember::Idle(idle) => {
+                let metric = &idle.metric;
+                translate_name(ctx, metric.kind.typ(), &metric.name, &metric.unit);
+                Ok(())
             }
-            let mut metrics: Vec<_> = scope.metrics().collect();
-            metrics.sort_unstable_by_key(|met| met.name());
+        };
+        if let Err(err) = extracted {
+            skip_metric(ctx, member.name(), err)?;
+            continue;
+        }
+        let Some(&index) = indices.get(&ctx.name) else {
+            indices.insert(ctx.name.clone(), families.len());
+            families.push(Family {
+                name: ctx.name.clone(),
+                quoted_name: ctx.quoted_name,
+                unit: ctx.unit.clone(),
+                typ: ctx.typ,
+                metrics: vec![(ctx.scope_name, member)],
+            });
+            continue;
+        };
+        let family = &mut families[index];
+        let same_scope = family
+            .metrics
+            .iter()
+            .any(|(scope_name, _)| *scope_name == ctx.scope_name);
+        if same_scope || family.typ != ctx.typ || family.unit != ctx.unit {
+            let err = ConversionError::ConflictingFamily {
+                scope: ctx.scope_name.to_owned(),
+                metric: member.name().to_owned(),
+                family: ctx.name.clone(),
+            };
+            skip_metric(ctx, member.name(), err)?;
+            continue;
+        }
+        family.metrics.push((ctx.scope_name, member));
+    }
+    Ok(families)
+}
 
-            for metric in metrics {
-                if extract_type_unit_and_name(&mut ctx, metric) {
-                    write_header(&mut ctx, metric.description())?;
-                    write_values(&mut ctx, metric.data())?;
-                } else {
-                    #[cfg(feature = "tracing")]
-                    tracing::warn!("Unsupported metric type {metric:?}");
+/// Skips the metric `metric_name` of the current scope because of `err` in lenient mode. Returns `err` otherwise.
+fn skip_metric(
+    ctx: &mut Context<'_, impl uWrite>,
+    metric_name: &str,
+    err: ConversionError,
+) -> Result<(), ConversionError> {
+    if !ctx.options.lenient {
+        return Err(err);
+    }
+    #[cfg(feature = "tracing")]
+    tracing::warn!("Skipping metric: {err}");
+    if let Some(report) = ctx.report.as_deref_mut() {
+        report.dropped.push(DroppedMetric {
+            scope: ctx.scope_name.to_owned(),
+            metric: metric_name.to_owned(),
+            reason: err,
+        });
+    }
+    Ok(())
+}
+
+/// Write `family` with a single header, followed by the values of all its metrics. The series of different scopes
+/// are told apart by the `otel_scope_name` label.
+fn write_family<'a, U: uWrite<Error = std::fmt::Error>>(
+    ctx: &mut Context<'a, U>,
+    family: &Family<'a>,
+) -> Result<(), ConversionError> {
+    ctx.name.clear();
+    ctx.name.push_str(&family.name);
+    ctx.quoted_name = family.quoted_name;
+    ctx.unit = family.unit.clone();
+    ctx.typ = family.typ;
+    let description = family
+        .metrics
+        .iter()
+        .map(|(_, member)| member.description())
+        .find(|description| !description.is_empty())
+        .unwrap_or_default();
+    write_header(ctx, description)?;
+
+    // Without the scope name, the series of a family spanning several scopes could not be told apart
+    ctx.scope_label = ctx.options.scope_info
+        || family
+            .metrics
+            .iter()
+            .any(|(scope_name, _)| *scope_name != family.metrics[0].0);
+    for &(scope_name, member) in &family.metrics {
+        ctx.scope_name = scope_name;
+        match member {
+            FamilyMember::Metric(metric) => {
+                if let Some(accumulator) = ctx.accumulator.as_deref_mut() {
+                    accumulator.begin_metric(scope_name, metric);
+                }
+                if ctx.report.is_some() {
+                    report_alterations(ctx, metric);
                 }
+                write_values(ctx, metric.data())?;
+            }
+            FamilyMember::Idle(idle) => {
+                if let Some(accumulator) = ctx.accumulator.as_deref_mut() {
+                    accumulator.select_metric(scope_name, &idle.metric.name);
+                }
+                write_idle_values(ctx, idle)?;
             }
         }
-        f.write_str("# EOF\n")?;
-        Ok(())
     }
+    Ok(())
+}
+
+/// Write the end of the exposition.
+fn write_eof<U: uWrite<Error = std::fmt::Error>>(
+    ctx: &mut Context<'_, U>,
+) -> Result<(), ConversionError> {
+    if ctx.format == Format::OpenMetrics {
+        ctx.f.write_str("# EOF\n")?;
+    }
+    Ok(())
 }
 
 fn write_target_info<U: uWrite>(
     f: &mut U,
+    options: &ConversionOptions,
+    format: Format,
     resource: &opentelemetry_sdk::Resource,
 ) -> Result<(), U::Error> {
-    f.write_str("# TYPE target info\n")?;
+    f.write_str(match format {
+        Format::OpenMetrics => "# TYPE target info\n",
+        // Prometheus has no info type
+        Format::Prometheus => "# TYPE target_info gauge\n",
+    })?;
     f.write_str("target_info{")?;
-    write_attrs_tuple(f, resource.iter())?;
+    write_attrs_tuple(f, options, resource.iter(), &[])?;
     f.write_str("} 1\n")?;
     Ok(())
 }
 
+/// Checks that `metric` can be converted and sets up `ctx` to write it.
 fn extract_type_unit_and_name(
-    ctx: &mut Context<'_, impl uWrite<Error = std::fmt::Error>>,
+    ctx: &mut Context<'_, impl uWrite>,
     metric: &Metric,
-) -> bool {
-    let Ok(typ) = get_type(metric.data()) else {
-        return false;
-    };
+) -> Result<(), ConversionError> {
+    let typ = get_type(metric.data(), ctx.accumulator.is_some()).map_err(|temporality| {
+        ConversionError::UnsupportedTemporality {
+            scope: ctx.scope_name.to_owned(),
+            metric: metric.name().to_owned(),
+            temporality,
+        }
+    })?;
+    if let Some(time) = get_times(metric.data())
+        .into_iter()
+        .flatten()
+        .find(|time| *time < SystemTime::UNIX_EPOCH)
+    {
+        return Err(ConversionError::InvalidTimestamp {
+            scope: ctx.scope_name.to_owned(),
+            metric: metric.name().to_owned(),
+            time,
+        });
+    }
+    translate_name(ctx, typ, metric.name(), metric.unit());
+    Ok(())
+}
+
+/// Sets up `ctx` to write a metric of type `typ` with the original `name` and `unit`.
+fn translate_name(ctx: &mut Context<'_, impl uWrite>, typ: &'static str, name: &str, unit: &str) {
     ctx.typ = typ;
-    ctx.unit = get_unit_suffixes(metric.unit());
+    ctx.unit = if ctx.options.writes_unit_suffixes() {
+        get_unit_suffixes(unit)
+    } else {
+        None
+    };
 
     ctx.name.clear();
-    let Ok(_) = write_sanitized_name(&mut ctx.name, metric.name());
+    if ctx.options.escapes_names() {
+        let Ok(_) = write_sanitized_name(&mut ctx.name, name);
+    } else {
+        ctx.name.push_str(name);
+    }
+    // Suffixes which are already part of the name are not repeated, see
+    // https://github.com/open-telemetry/opentelemetry-specification/blob/v1.45.0/specification/compatibility/prometheus_and_openmetrics.md#metric-metadata-1
+    if typ == "counter" && ctx.options.writes_total_suffix() {
+        strip_suffix(&mut ctx.name, "total");
+    }
     if let Some(ref unit) = ctx.unit {
+        strip_suffix(&mut ctx.name, unit);
         ctx.name.push('_');
         ctx.name.push_str(unit);
     }
+    ctx.quoted_name = !ctx.options.escapes_names() && !is_legacy_metric_name(&ctx.name);
+}
+
+/// Removes `_{suffix}` from the end of `name`, unless nothing would be left.
+fn strip_suffix(name: &mut String, suffix: &str) {
+    if let Some(stem) = name.strip_suffix(suffix)
+        && let Some(stem) = stem.strip_suffix('_')
+        && !stem.is_empty()
+    {
+        name.truncate(stem.len());
+    }
+}
+
+/// Records in the report of `ctx` how `metric` is altered when written. Make sure to call
+/// [extract_type_unit_and_name] first.
+fn report_alterations(ctx: &mut Context<'_, impl uWrite>, metric: &Metric) {
+    let Some(report) = ctx.report.as_deref_mut() else {
+        return;
+    };
+    if ctx.name != metric.name() {
+        report.renamed.push(RenamedMetric {
+            scope: ctx.scope_name.to_owned(),
+            original: metric.name().to_owned(),
+            name: ctx.name.clone(),
+        });
+    }
+    // Units are only dropped if they cannot be converted, not if suffixes are disabled
+    if ctx.options.writes_unit_suffixes() && ctx.unit.is_none() && !metric.unit().is_empty() {
+        report.dropped_units.push(DroppedUnit {
+            scope: ctx.scope_name.to_owned(),
+            metric: metric.name().to_owned(),
+            unit: metric.unit().to_owned(),
+        });
+    }
 
-    true
+    let keys = get_attribute_keys(metric.data());
+    let names: Vec<_> = keys
+        .iter()
+        .map(|key| label_name(&ctx.options, key.as_str()))
+        .collect();
+    let labels: Vec<_> = make_scope_name_attrs(ctx.scope_label, ctx.scope_name)
+        .into_iter()
+        .collect();
+    for (key, name) in keys.into_iter().zip(&names) {
+        let label = exported_label_name(name.clone(), &labels, &names);
+        let already_reported = || {
+            report
+                .sanitized_labels
+                .iter()
+                .any(|l| l.metric == ctx.name && l.original == key.as_str())
+        };
+        if label != key.as_str() && !already_reported() {
+            report.sanitized_labels.push(SanitizedLabel {
+                metric: ctx.name.clone(),
+                original: key.as_str().to_owned(),
+                label: label.into_owned(),
+            });
+        }
+    }
+}
+
+/// Gets the distinct attribute keys of all data points of this [AggregatedMetrics].
+fn get_attribute_keys(metric: &AggregatedMetrics) -> BTreeSet<&Key> {
+    fn get_metric_data_keys<T>(metric_data: &MetricData<T>) -> BTreeSet<&Key> {
+        let mut out = BTreeSet::new();
+        match metric_data {
+            MetricData::Gauge(gauge) => {
+                for point in gauge.data_points() {
+                    out.extend(point.attributes().map(|kv| &kv.key));
+                }
+            }
+            MetricData::Sum(sum) => {
+                for point in sum.data_points() {
+                    out.extend(point.attributes().map(|kv| &kv.key));
+                }
+            }
+            MetricData::Histogram(hist) => {
+                for point in hist.data_points() {
+                    out.extend(point.attributes().map(|kv| &kv.key));
+                }
+            }
+            MetricData::ExponentialHistogram(hist) => {
+                for point in hist.data_points() {
+                    out.extend(point.attributes().map(|kv| &kv.key));
+                }
+            }
+        }
+        out
+    }
+    match metric {
+        AggregatedMetrics::F64(metric_data) => get_metric_data_keys(metric_data),
+        AggregatedMetrics::U64(metric_data) => get_metric_data_keys(metric_data),
+        AggregatedMetrics::I64(metric_data) => get_metric_data_keys(metric_data),
+    }
 }
 
 /// Gets the OpenMetrics metric type for this [AggregatedMetrics].
-/// Returns `Err(())` for unsupported metric types.
-fn get_type(metric: &AggregatedMetrics) -> Result<&'static str, ()> {
-    fn get_metric_data_type<T>(metric_data: &MetricData<T>) -> Result<&'static str, ()> {
+/// Returns the offending [Temporality] for unsupported metrics.
+/// Sums and histograms with delta temporality are only supported when they are accumulated.
+fn get_type(metric: &AggregatedMetrics, accumulate: bool) -> Result<&'static str, Temporality> {
+    fn get_metric_data_type<T>(
+        metric_data: &MetricData<T>,
+        accumulate: bool,
+    ) -> Result<&'static str, Temporality> {
+        let supported = |temporality| match temporality {
+            Temporality::Delta if !accumulate => Err(temporality),
+            _ => Ok(()),
+        };
         match metric_data {
             MetricData::Gauge(_) => Ok("gauge"),
             MetricData::Sum(sum) => {
+                supported(sum.temporality())?;
                 if sum.is_monotonic() {
                     Ok("counter")
                 } else {
@@ -155,41 +670,79 @@ fn get_type(metric: &AggregatedMetrics) -> Result<&'static str, ()> {
                 }
             }
             MetricData::Histogram(hist) => {
-                if hist.temporality() == Temporality::Cumulative {
-                    Ok("histogram")
-                } else {
-                    Err(())
-                }
+                supported(hist.temporality())?;
+                Ok("histogram")
+            }
+            MetricData::ExponentialHistogram(hist) => {
+                supported(hist.temporality())?;
+                Ok("histogram")
             }
-            _ => Err(()),
         }
     }
     match metric {
-        AggregatedMetrics::F64(metric_data) => get_metric_data_type(metric_data),
-        AggregatedMetrics::U64(metric_data) => get_metric_data_type(metric_data),
-        AggregatedMetrics::I64(metric_data) => get_metric_data_type(metric_data),
+        AggregatedMetrics::F64(metric_data) => get_metric_data_type(metric_data, accumulate),
+        AggregatedMetrics::U64(metric_data) => get_metric_data_type(metric_data, accumulate),
+        AggregatedMetrics::I64(metric_data) => get_metric_data_type(metric_data, accumulate),
     }
 }
 
-/// Write the current metric's metadata. Make sure to call [extract_type_unit_and_name] first.
+/// Gets the collection time and the start time of this [AggregatedMetrics].
+fn get_times(metric: &AggregatedMetrics) -> [Option<SystemTime>; 2] {
+    fn get_metric_data_times<T>(metric_data: &MetricData<T>) -> [Option<SystemTime>; 2] {
+        match metric_data {
+            MetricData::Gauge(gauge) => [Some(gauge.time()), gauge.start_time()],
+            MetricData::Sum(sum) => [Some(sum.time()), Some(sum.start_time())],
+            MetricData::Histogram(hist) => [Some(hist.time()), Some(hist.start_time())],
+            MetricData::ExponentialHistogram(hist) => [Some(hist.time()), Some(hist.start_time())],
+        }
+    }
+    match metric {
+        AggregatedMetrics::F64(metric_data) => get_metric_data_times(metric_data),
+        AggregatedMetrics::U64(metric_data) => get_metric_data_times(metric_data),
+        AggregatedMetrics::I64(metric_data) => get_metric_data_times(metric_data),
+    }
+}
+
+/// Write the current metric family's metadata. Make sure to call [extract_type_unit_and_name] first.
 #[inline]
 fn write_header<U: uWrite>(ctx: &mut Context<'_, U>, description: &str) -> Result<(), U::Error> {
     let Context {
-        f, name, unit, typ, ..
+        f,
+        name,
+        quoted_name,
+        unit,
+        typ,
+        format,
+        options,
+        ..
     } = ctx;
-    for x in &["# TYPE ", name, " ", typ, "\n"] {
-        f.write_str(x)?;
+    let name = MetricName {
+        name,
+        quoted: *quoted_name,
+    };
+    if *format == Format::Prometheus {
+        // Prometheus counter families are named after their samples
+        let suffix = if *typ == "counter" && options.writes_total_suffix() {
+            "_total"
+        } else {
+            ""
+        };
+        if !description.is_empty() {
+            uwrite!(f, "# HELP {} ", name.family(suffix))?;
+            write_escaped_help(f, description)?;
+            f.write_char('\n')?;
+        }
+        uwriteln!(f, "# TYPE {} {}", name.family(suffix), typ)?;
+        return Ok(());
     }
 
+    uwriteln!(f, "# TYPE {} {}", name.family(""), typ)?;
+
     if let Some(unit) = unit {
-        for x in &["# UNIT ", name, " ", unit, "\n"] {
-            f.write_str(x)?;
-        }
+        uwriteln!(f, "# UNIT {} {}", name.family(""), unit.as_ref())?;
     }
     if !description.is_empty() {
-        f.write_str("# HELP ")?;
-        f.write_str(name)?;
-        f.write_str(" ")?;
+        uwrite!(f, "# HELP {} ", name.family(""))?;
         write_escaped(f, description)?;
         f.write_char('\n')?;
     }
@@ -198,12 +751,16 @@ fn write_header<U: uWrite>(ctx: &mut Context<'_, U>, description: &str) -> Resul
 
 /// Write a otel_scope metric of type info for all scopes in `metrics`
 /// according to the [spec](https://github.com/open-telemetry/opentelemetry-specification/blob/v1.45.0/specification/compatibility/prometheus_and_openmetrics.md#instrumentation-scope-1).
-#[cfg(feature = "otel_scope_info")]
 fn write_otel_scope_info<U: uWrite>(
     f: &mut U,
+    options: &ConversionOptions,
+    format: Format,
     metrics: &'_ Vec<&ScopeMetrics>,
 ) -> Result<(), U::Error> {
-    f.write_str("# TYPE otel_scope info\n")?;
+    f.write_str(match format {
+        Format::OpenMetrics => "# TYPE otel_scope info\n",
+        Format::Prometheus => "# TYPE otel_scope_info gauge\n",
+    })?;
 
     for scope in metrics {
         let otel_attrs = &[
@@ -214,7 +771,7 @@ fn write_otel_scope_info<U: uWrite>(
             ),
         ];
         f.write_str("otel_scope_info{")?;
-        write_attrs(f, otel_attrs.iter().chain(scope.scope().attributes()))?;
+        write_attrs(f, options, scope.scope().attributes(), otel_attrs)?;
         f.write_str("} 1\n")?;
     }
     Ok(())
@@ -226,185 +783,709 @@ fn write_values<U: uWrite>(
     metric: &AggregatedMetrics,
 ) -> Result<(), U::Error> {
     match metric {
-        AggregatedMetrics::F64(metric_data) => {
-            match metric_data {
-                MetricData::Gauge(gauge) => write_gauge(ctx, gauge),
-                MetricData::Sum(sum) => write_counter(ctx, sum),
-                MetricData::Histogram(histogram) => write_histogram(ctx, histogram),
-                _ => unimplemented!("only gauge/sum/histogram metrics should be constructible"),
-                // See https://github.com/open-telemetry/opentelemetry-specification/blob/v1.45.0/specification/compatibility/prometheus_and_openmetrics.md#exponential-histograms
-                // for exponential histograms
+        AggregatedMetrics::F64(metric_data) => match metric_data {
+            MetricData::Gauge(gauge) => write_gauge(ctx, gauge),
+            MetricData::Sum(sum) => write_counter(ctx, sum),
+            MetricData::Histogram(histogram) => write_histogram(ctx, histogram),
+            MetricData::ExponentialHistogram(histogram) => {
+                write_exponential_histogram(ctx, histogram)
             }
-        }
+        },
         AggregatedMetrics::U64(metric_data) => match metric_data {
             MetricData::Gauge(gauge) => write_gauge(ctx, gauge),
             MetricData::Sum(sum) => write_counter(ctx, sum),
             MetricData::Histogram(histogram) => write_histogram(ctx, histogram),
-            _ => unimplemented!("only gauge/sum/histogram metrics should be constructible"),
+            MetricData::ExponentialHistogram(histogram) => {
+                write_exponential_histogram(ctx, histogram)
+            }
         },
         AggregatedMetrics::I64(metric_data) => match metric_data {
             MetricData::Gauge(gauge) => write_gauge(ctx, gauge),
             MetricData::Sum(sum) => write_counter(ctx, sum),
             MetricData::Histogram(histogram) => write_histogram(ctx, histogram),
-            _ => unimplemented!("only gauge/sum/histogram metrics should be constructible"),
+            MetricData::ExponentialHistogram(histogram) => {
+                write_exponential_histogram(ctx, histogram)
+            }
         },
     }
 }
 
-fn write_histogram<T: FastDisplay + Copy, U: uWrite>(
+fn write_histogram<T: FastDisplay + Accumulate + ExemplarValue, U: uWrite>(
     ctx: &mut Context<'_, U>,
     histogram: &Histogram<T>,
 ) -> Result<(), U::Error> {
-    let scope_name_attrs = make_scope_name_attrs(ctx.scope_name);
-    let ts = to_timestamp(histogram.time());
-    let created = to_timestamp(histogram.start_time());
-    ctx.attr_buffer.clear();
+    if histogram.temporality() == Temporality::Delta
+        && let Some(accumulator) = ctx.accumulator.as_deref_mut()
+    {
+        let mut exemplars = HashMap::new();
+        for point in histogram.data_points() {
+            let key = accumulator.add_histogram(point, histogram.start_time());
+            exemplars.insert(
+                key,
+                collect_exemplars(&ctx.options, ctx.format, point.exemplars()),
+            );
+        }
+        return write_accumulated_histograms::<T, U>(ctx, histogram.time(), exemplars);
+    }
+
+    let scope_name_attrs = make_scope_name_attrs(ctx.scope_label, ctx.scope_name);
+    let ts = sample_timestamp(&ctx.options, ctx.format, histogram.time());
+    let created = ctx.options.created && ctx.format == Format::OpenMetrics;
     let attrs = &mut ctx.attr_buffer;
-    let Ok(()) = write_attrs(attrs, scope_name_attrs.iter());
-    uwriteln!(
-        ctx.f,
-        "{}_created{{{}}} {} {}"
-        ctx.name,
-        attrs,
-        created,
-        ts,
-    )?;
-    assert_eq!(
-        histogram.temporality(),
-        Temporality::Cumulative,
-        "Only cumulative Histograms are supported"
-    );
+    let name = MetricName {
+        name: &ctx.name,
+        quoted: ctx.quoted_name,
+    };
 
     let mut points: Vec<_> = histogram.data_points().collect();
     points.sort_by_cached_key(|p| hash_attrs(p.attributes()));
 
     for point in points {
+        let values = HistogramValues {
+            count: point.count(),
+            sum: Some(point.sum()),
+            min: point.min(),
+            max: point.max(),
+            buckets: std::iter::zip(point.bounds(), point.bucket_counts()),
+            exemplars: collect_exemplars(&ctx.options, ctx.format, point.exemplars()),
+            created: created.then_some(histogram.start_time()),
+        };
+        attrs.clear();
+        let Ok(()) = write_attrs(
+            attrs,
+            &ctx.options,
+            point.attributes(),
+            scope_name_attrs.as_slice(),
+        );
+        write_histogram_values(&mut ctx.f, &ctx.options, name, attrs, ts, values)?;
+    }
+    Ok(())
+}
+
+/// Write the running totals of all histogram series of the current metric at `time`, see [DeltaAccumulator]. The
+/// series with data points in the current conversion get their `exemplars`, looked up by series key.
+fn write_accumulated_histograms<T: FastDisplay + Accumulate, U: uWrite>(
+    ctx: &mut Context<'_, U>,
+    time: SystemTime,
+    mut exemplars: HashMap<String, Vec<ExemplarData<'_>>>,
+) -> Result<(), U::Error> {
+    let Some(accumulator) = ctx.accumulator.as_deref() else {
+        return Ok(());
+    };
+    let scope_name_attrs = make_scope_name_attrs(ctx.scope_label, ctx.scope_name);
+    let ts = sample_timestamp(&ctx.options, ctx.format, time);
+    let created = ctx.options.created && ctx.format == Format::OpenMetrics;
+    let attrs = &mut ctx.attr_buffer;
+    let name = MetricName {
+        name: &ctx.name,
+        quoted: ctx.quoted_name,
+    };
+
+    for (key, series) in accumulator.series::<T>() {
+        let SeriesValues::Histogram(totals) = &series.values else {
+            continue;
+        };
+        let values = HistogramValues {
+            count: totals.count,
+            sum: Some(totals.sum),
+            min: totals.min,
+            max: totals.max,
+            buckets: std::iter::zip(
+                totals.bounds.iter().copied(),
+                totals.bucket_counts.iter().copied(),
+            ),
+            exemplars: exemplars.remove(key).unwrap_or_default(),
+            created: created.then_some(series.start_time),
+        };
         attrs.clear();
-        let Ok(()) = write_attrs(attrs, point.attributes().chain(scope_name_attrs.iter()));
+        let Ok(()) = write_attrs(
+            attrs,
+            &ctx.options,
+            series.attributes.iter(),
+            scope_name_attrs.as_slice(),
+        );
+        write_histogram_values(&mut ctx.f, &ctx.options, name, attrs, ts, values)?;
+    }
+    Ok(())
+}
+
+/// The values of a single histogram series.
+struct HistogramValues<'a, T, B: Iterator<Item = (f64, u64)>> {
+    count: u64,
+    /// the sum of the observations, if it is written
+    sum: Option<T>,
+    min: Option<T>,
+    max: Option<T>,
+    /// (upper bound, count) pairs of the finite buckets
+    buckets: B,
+    /// the exemplars to attach to the buckets
+    exemplars: Vec<ExemplarData<'a>>,
+    /// the start time of the series, if a `_created` sample is written
+    created: Option<SystemTime>,
+}
 
+fn write_histogram_values<T: FastDisplay + Copy, B: Iterator<Item = (f64, u64)>, U: uWrite>(
+    f: &mut U,
+    options: &ConversionOptions,
+    name: MetricName,
+    attrs: &mut String,
+    ts: impl uDisplay + Copy,
+    values: HistogramValues<'_, T, B>,
+) -> Result<(), U::Error> {
+    uwriteln!(
+        f,
+        "{}{}}} {}{}",
+        name.sample("_count", !attrs.is_empty()),
+        attrs,
+        values.count.fast_display(),
+        ts
+    )?;
+    if let Some(sum) = values.sum {
         uwriteln!(
-            ctx.f,
-            "{}_count{{{}}} {} {}",
-            ctx.name,
+            f,
+            "{}{}}} {}{}",
+            name.sample("_sum", !attrs.is_empty()),
             attrs,
-            point.count().fast_display(),
-            ts
+            sum.fast_display(),
+            ts,
         )?;
-        uwriteln!(
-            ctx.f,
-            "{}_sum{{{}}} {} {}",
-            ctx.name,
+    }
+
+    if options.histogram_min_max {
+        write_min_max(f, name, attrs, values.min, values.max, ts)?;
+    }
+
+    let had_attrs = !attrs.is_empty();
+    if had_attrs {
+        attrs.push(',');
+    }
+    let mut cumulative_count = 0u64;
+    let mut lower_bound = f64::NEG_INFINITY;
+    for (bound, count) in values.buckets {
+        cumulative_count = cumulative_count.wrapping_add(count);
+        uwrite!(
+            // Not using write! here is a ~19% speedup
+            f,
+            "{}{}le=\"{}\"}} {}{}"
+            name.sample("_bucket", true),
             attrs,
-            point.sum().fast_display(),
+            bound.fast_display(),
+            cumulative_count.fast_display(),
             ts,
         )?;
+        // writeln!(
+        //     f,
+        //     "{name}_bucket{{{attrs}le=\"{bound}\"}} {count} {ts}",
+        //     bound = bound.fast_display(),
+        //     count = cumulative_count.fast_display(),
+        // )?;
+        let range = (Bound::Excluded(lower_bound), Bound::Included(bound));
+        if let Some(exemplar) = latest_exemplar(&values.exemplars, range) {
+            write_exemplar(f, options, exemplar)?;
+        }
+        f.write_char('\n')?;
+        lower_bound = bound;
+    }
+    uwrite!(
+        f,
+        "{}{}le=\"+Inf\"}} {}{}",
+        name.sample("_bucket", true),
+        attrs,
+        values.count.fast_display(),
+        ts,
+    )?;
+    let range = (Bound::Excluded(lower_bound), Bound::Unbounded);
+    if let Some(exemplar) = latest_exemplar(&values.exemplars, range) {
+        write_exemplar(f, options, exemplar)?;
+    }
+    f.write_char('\n')?;
 
-        #[cfg(feature = "histogram-min-max")]
-        {
-            // Non-compliant but useful
-            // TODO: Expose as a separate gauge?
-            if let Some(min) = point.min() {
-                uwriteln!(
-                    ctx.f,
-                    "{}_min{{{}}} {} {}",
-                    ctx.name,
-                    attrs,
-                    min.fast_display(),
-                    ts,
-                )?;
-            }
-            if let Some(max) = point.max() {
-                uwriteln!(
-                    ctx.f,
-                    "{}_max{{{}}} {} {}",
-                    ctx.name,
-                    attrs,
-                    max.fast_display(),
-                    ts,
-                )?;
-            }
+    if let Some(created) = values.created {
+        if had_attrs {
+            attrs.pop();
         }
+        write_created(f, name, attrs, created, ts)?;
+    }
+    Ok(())
+}
+
+/// Write the `_created` sample of a series which started at `created`.
+fn write_created<U: uWrite>(
+    f: &mut U,
+    name: MetricName,
+    attrs: &str,
+    created: SystemTime,
+    ts: impl uDisplay,
+) -> Result<(), U::Error> {
+    uwriteln!(
+        f,
+        "{}{}}} {}{}",
+        name.sample("_created", !attrs.is_empty()),
+        attrs,
+        to_timestamp(created),
+        ts
+    )
+}
 
-        if !attrs.is_empty() {
-            attrs.push(',');
-        }
-        let mut cumulative_count = 0;
-        for (bound, count) in std::iter::zip(point.bounds(), point.bucket_counts()) {
-            cumulative_count += count;
-            uwriteln!(
-                // Not using write! here is a ~19% speedup
-                ctx.f,
-                "{}_bucket{{{}le=\"{}\"}} {} {}"
-                ctx.name,
-                attrs,
-                bound.fast_display(),
-                cumulative_count.fast_display(),
-                ts,
-            )?;
-            // writeln!(
-            //     f,
-            //     "{name}_bucket{{{attrs}le=\"{bound}\"}} {count} {ts}",
-            //     bound = bound.fast_display(),
-            //     count = cumulative_count.fast_display(),
-            // )?;
+/// Write the `_min` and `_max` lines of a histogram data point.
+fn write_min_max<T: FastDisplay + Copy, U: uWrite>(
+    f: &mut U,
+    name: MetricName,
+    attrs: &str,
+    min: Option<T>,
+    max: Option<T>,
+    ts: impl uDisplay,
+) -> Result<(), U::Error> {
+    // Non-compliant but useful
+    // TODO: Expose as a separate gauge?
+    let labels = !attrs.is_empty();
+    if let Some(min) = min {
+        let name = name.sample("_min", labels);
+        uwriteln!(f, "{}{}}} {}{}", name, attrs, min.fast_display(), ts)?;
+    }
+    if let Some(max) = max {
+        let name = name.sample("_max", labels);
+        uwriteln!(f, "{}{}}} {}{}", name, attrs, max.fast_display(), ts)?;
+    }
+    Ok(())
+}
+
+/// Write an exponential histogram as a classic histogram according to the
+/// [spec](https://github.com/open-telemetry/opentelemetry-specification/blob/v1.45.0/specification/compatibility/prometheus_and_openmetrics.md#exponential-histograms).
+///
+/// The negative buckets, the zero bucket and the positive buckets are expanded into cumulative `_bucket` lines in
+/// ascending order of their upper bound.
+/// Data points with observations in negative buckets are written without `_sum`, which OpenMetrics forbids for
+/// histograms with negative bucket thresholds.
+fn write_exponential_histogram<T: FastDisplay + Accumulate + ExemplarValue, U: uWrite>(
+    ctx: &mut Context<'_, U>,
+    histogram: &ExponentialHistogram<T>,
+) -> Result<(), U::Error> {
+    if histogram.temporality() == Temporality::Delta
+        && let Some(accumulator) = ctx.accumulator.as_deref_mut()
+    {
+        let mut exemplars = HashMap::new();
+        for point in histogram.data_points() {
+            let key = accumulator.add_exponential_histogram(point, histogram.start_time());
+            exemplars.insert(
+                key,
+                collect_exemplars(&ctx.options, ctx.format, point.exemplars()),
+            );
         }
-        uwriteln!(
-            ctx.f,
-            "{}_bucket{{{}le=\"+Inf\"}} {} {}",
-            ctx.name,
+        return write_accumulated_exponential_histograms::<T, U>(ctx, histogram.time(), exemplars);
+    }
+
+    let scope_name_attrs = make_scope_name_attrs(ctx.scope_label, ctx.scope_name);
+    let ts = sample_timestamp(&ctx.options, ctx.format, histogram.time());
+    let created = ctx.options.created && ctx.format == Format::OpenMetrics;
+    let attrs = &mut ctx.attr_buffer;
+    let name = MetricName {
+        name: &ctx.name,
+        quoted: ctx.quoted_name,
+    };
+
+    let mut points: Vec<_> = histogram.data_points().collect();
+    points.sort_by_cached_key(|p| hash_attrs(p.attributes()));
+
+    for point in points {
+        let buckets = ExponentialBuckets::new(
+            point.scale(),
+            point.negative_bucket(),
+            point.positive_bucket(),
+            ctx.options.max_exponential_buckets,
+        );
+        let values = HistogramValues {
+            count: point.count() as u64,
+            sum: (!buckets.has_negative_counts()).then(|| point.sum()),
+            min: point.min(),
+            max: point.max(),
+            buckets: buckets.iter(point.zero_threshold(), point.zero_count()),
+            exemplars: collect_exemplars(&ctx.options, ctx.format, point.exemplars()),
+            created: created.then_some(histogram.start_time()),
+        };
+        attrs.clear();
+        let Ok(()) = write_attrs(
             attrs,
-            point.count().fast_display(),
-            ts,
-        )?;
+            &ctx.options,
+            point.attributes(),
+            scope_name_attrs.as_slice(),
+        );
+        write_histogram_values(&mut ctx.f, &ctx.options, name, attrs, ts, values)?;
     }
     Ok(())
 }
 
-fn write_counter<T: FastDisplay + Copy, U: uWrite>(
+/// Write the running totals of all exponential histogram series of the current metric at `time` as classic
+/// histograms, see [write_exponential_histogram] and [write_accumulated_histograms].
+fn write_accumulated_exponential_histograms<T: FastDisplay + Accumulate, U: uWrite>(
+    ctx: &mut Context<'_, U>,
+    time: SystemTime,
+    mut exemplars: HashMap<String, Vec<ExemplarData<'_>>>,
+) -> Result<(), U::Error> {
+    let Some(accumulator) = ctx.accumulator.as_deref() else {
+        return Ok(());
+    };
+    let scope_name_attrs = make_scope_name_attrs(ctx.scope_label, ctx.scope_name);
+    let ts = sample_timestamp(&ctx.options, ctx.format, time);
+    let created = ctx.options.created && ctx.format == Format::OpenMetrics;
+    let attrs = &mut ctx.attr_buffer;
+    let name = MetricName {
+        name: &ctx.name,
+        quoted: ctx.quoted_name,
+    };
+
+    for (key, series) in accumulator.series::<T>() {
+        let SeriesValues::ExponentialHistogram(totals) = &series.values else {
+            continue;
+        };
+        let mut buckets = totals.buckets.clone();
+        buckets.limit(ctx.options.max_exponential_buckets);
+        let values = HistogramValues {
+            count: totals.count,
+            sum: (!buckets.has_negative_counts()).then_some(totals.sum),
+            min: totals.min,
+            max: totals.max,
+            buckets: buckets.iter(totals.zero_threshold, totals.zero_count),
+            exemplars: exemplars.remove(key).unwrap_or_default(),
+            created: created.then_some(series.start_time),
+        };
+        attrs.clear();
+        let Ok(()) = write_attrs(
+            attrs,
+            &ctx.options,
+            series.attributes.iter(),
+            scope_name_attrs.as_slice(),
+        );
+        write_histogram_values(&mut ctx.f, &ctx.options, name, attrs, ts, values)?;
+    }
+    Ok(())
+}
+
+/// The negative and positive bucket counts of an exponential histogram data point, possibly downscaled.
+#[derive(Debug, Clone)]
+pub(crate) struct ExponentialBuckets {
+    scale: i8,
+    negative_offset: i32,
+    negative_counts: Vec<u64>,
+    positive_offset: i32,
+    positive_counts: Vec<u64>,
+}
+
+impl ExponentialBuckets {
+    /// The lowest scale supported by OpenTelemetry, at which a data point still spans up to three buckets per sign.
+    const MIN_SCALE: i8 = -10;
+
+    /// Copies the buckets, merging adjacent buckets until no more than `max_buckets` buckets
+    /// (including the zero bucket) remain.
+    fn new(
+        scale: i8,
+        negative: &ExponentialBucket,
+        positive: &ExponentialBucket,
+        max_buckets: Option<usize>,
+    ) -> Self {
+        let mut buckets = ExponentialBuckets {
+            scale,
+            negative_offset: negative.offset(),
+            negative_counts: negative.counts().collect(),
+            positive_offset: positive.offset(),
+            positive_counts: positive.counts().collect(),
+        };
+        buckets.limit(max_buckets);
+        buckets
+    }
+
+    /// Merges adjacent buckets until no more than `max_buckets` buckets (including the zero bucket) remain.
+    fn limit(&mut self, max_buckets: Option<usize>) {
+        if let Some(max_buckets) = max_buckets {
+            while self.negative_counts.len() + 1 + self.positive_counts.len() > max_buckets
+                && self.scale > Self::MIN_SCALE
+            {
+                self.downscale();
+            }
+        }
+    }
+
+    /// Adds the counts of `other`, lowering the scale of either to the common scale. Adjacent buckets are merged
+    /// further until no more than `max_buckets` buckets per sign remain.
+    fn add(&mut self, other: &ExponentialBuckets, max_buckets: usize) {
+        fn add_counts(
+            offset: &mut i32,
+            counts: &mut Vec<u64>,
+            other_offset: i32,
+            other_counts: &[u64],
+        ) {
+            if other_counts.is_empty() {
+                return;
+            }
+            if counts.is_empty() {
+                *offset = other_offset;
+                *counts = other_counts.to_vec();
+                return;
+            }
+            let start = (*offset).min(other_offset);
+            let end = (*offset + counts.len() as i32).max(other_offset + other_counts.len() as i32);
+            let mut merged = vec![0; (end - start) as usize];
+            for (i, &count) in counts.iter().enumerate() {
+                let total = &mut merged[(*offset - start) as usize + i];
+                *total = total.wrapping_add(count);
+            }
+            for (i, &count) in other_counts.iter().enumerate() {
+                let total = &mut merged[(other_offset - start) as usize + i];
+                *total = total.wrapping_add(count);
+            }
+            *offset = start;
+            *counts = merged;
+        }
+
+        let mut other = other.clone();
+        while other.scale > self.scale {
+            other.downscale();
+        }
+        while self.scale > other.scale {
+            self.downscale();
+        }
+        add_counts(
+            &mut self.negative_offset,
+            &mut self.negative_counts,
+            other.negative_offset,
+            &other.negative_counts,
+        );
+        add_counts(
+            &mut self.positive_offset,
+            &mut self.positive_counts,
+            other.positive_offset,
+            &other.positive_counts,
+        );
+        while self.negative_counts.len().max(self.positive_counts.len()) > max_buckets
+            && self.scale > Self::MIN_SCALE
+        {
+            self.downscale();
+        }
+    }
+
+    /// Returns `true` if any of the negative buckets has a count.
+    fn has_negative_counts(&self) -> bool {
+        self.negative_counts.iter().any(|&count| count > 0)
+    }
+
+    /// Lowers the scale by one, merging each pair of adjacent buckets.
+    fn downscale(&mut self) {
+        fn merge_pairs(offset: &mut i32, counts: &mut Vec<u64>) {
+            if counts.is_empty() {
+                return;
+            }
+            // Bucket `i` at scale `s` is contained in bucket `i >> 1` at scale `s - 1`
+            let new_offset = *offset >> 1;
+            let new_index = |i: usize| ((*offset + i as i32) >> 1) - new_offset;
+            let mut merged = vec![0; new_index(counts.len() - 1) as usize + 1];
+            for (i, &count) in counts.iter().enumerate() {
+                let total = &mut merged[new_index(i) as usize];
+                *total = total.wrapping_add(count);
+            }
+            *offset = new_offset;
+            *counts = merged;
+        }
+        merge_pairs(&mut self.negative_offset, &mut self.negative_counts);
+        merge_pairs(&mut self.positive_offset, &mut self.positive_counts);
+        self.scale -= 1;
+    }
+
+    /// Returns the lower boundary of the bucket with `index`, i.e. `base^index`.
+    fn lower_boundary(&self, index: i32) -> f64 {
+        // base = 2^(2^-scale), so base^index = 2^(index * 2^-scale)
+        (index as f64 * 2f64.powi(-(self.scale as i32))).exp2()
+    }
+
+    /// Iterates over all (upper bound, count) pairs in ascending order of the upper bound.
+    fn iter(&self, zero_threshold: f64, zero_count: u64) -> impl Iterator<Item = (f64, u64)> {
+        // Negative bucket `i` holds values in [-base^(i+1), -base^i)
+        let negative = self
+            .negative_counts
+            .iter()
+            .enumerate()
+            .rev()
+            .map(|(i, &count)| (-self.lower_boundary(self.negative_offset + i as i32), count));
+        let zero = std::iter::once((zero_threshold, zero_count));
+        // Positive bucket `i` holds values in (base^i, base^(i+1)]. At low scales, the upper bound of the highest
+        // bucket can overflow to infinity, that bucket is covered by the `+Inf` bucket.
+        let positive = self
+            .positive_counts
+            .iter()
+            .enumerate()
+            .map(|(i, &count)| {
+                (
+                    self.lower_boundary(self.positive_offset + i as i32 + 1),
+                    count,
+                )
+            })
+            .filter(|(bound, _)| bound.is_finite());
+        negative.chain(zero).chain(positive)
+    }
+}
+
+fn write_counter<T: FastDisplay + Accumulate + ExemplarValue, U: uWrite>(
     ctx: &mut Context<'_, U>,
     sum: &Sum<T>,
 ) -> Result<(), U::Error> {
+    if sum.temporality() == Temporality::Delta
+        && let Some(accumulator) = ctx.accumulator.as_deref_mut()
+    {
+        let mut exemplars = HashMap::new();
+        for point in sum.data_points() {
+            let key = accumulator.add_sum(point, sum.start_time());
+            exemplars.insert(
+                key,
+                collect_exemplars(&ctx.options, ctx.format, point.exemplars()),
+            );
+        }
+        return write_accumulated_sums::<T, U>(ctx, sum.is_monotonic(), sum.time(), exemplars);
+    }
+
     let attrs = &mut ctx.attr_buffer;
-    let scope_name_attrs = make_scope_name_attrs(ctx.scope_name);
-    assert_eq!(
-        sum.temporality(),
-        opentelemetry_sdk::metrics::Temporality::Cumulative,
-        "Only cumulative sums are supported"
-    );
+    let scope_name_attrs = make_scope_name_attrs(ctx.scope_label, ctx.scope_name);
 
     let mut points: Vec<_> = sum.data_points().collect();
     points.sort_by_cached_key(|p| hash_attrs(p.attributes()));
 
-    let ts = to_timestamp(sum.time());
-
-    if sum.is_monotonic() {
-        for point in points {
-            attrs.clear();
-            let Ok(()) = write_attrs(attrs, point.attributes().chain(scope_name_attrs.iter()));
-            uwriteln!(
-                ctx.f,
-                "{}_total{{{}}} {} {}",
-                ctx.name,
-                attrs,
-                point.value().fast_display(),
-                ts,
-            )?;
-        }
-        Ok(())
+    let ts = sample_timestamp(&ctx.options, ctx.format, sum.time());
+    let name = MetricName {
+        name: &ctx.name,
+        quoted: ctx.quoted_name,
+    };
+    // Non-monotonic sums are written as gauges, which have no `_created` samples
+    let created = sum.is_monotonic() && ctx.options.created && ctx.format == Format::OpenMetrics;
+
+    for point in points {
+        let values = SumValues {
+            value: point.value(),
+            monotonic: sum.is_monotonic(),
+            exemplars: collect_exemplars(&ctx.options, ctx.format, point.exemplars()),
+            created: created.then_some(sum.start_time()),
+        };
+        attrs.clear();
+        let Ok(()) = write_attrs(
+            attrs,
+            &ctx.options,
+            point.attributes(),
+            scope_name_attrs.as_slice(),
+        );
+        write_sum_values(&mut ctx.f, &ctx.options, name, attrs, ts, values)?;
+    }
+    Ok(())
+}
+
+/// Write the running totals of all sum series of the current metric at `time`, see [DeltaAccumulator]. The series
+/// with data points in the current conversion get their `exemplars`, looked up by series key.
+fn write_accumulated_sums<T: FastDisplay + Accumulate, U: uWrite>(
+    ctx: &mut Context<'_, U>,
+    monotonic: bool,
+    time: SystemTime,
+    mut exemplars: HashMap<String, Vec<ExemplarData<'_>>>,
+) -> Result<(), U::Error> {
+    let Some(accumulator) = ctx.accumulator.as_deref() else {
+        return Ok(());
+    };
+    let scope_name_attrs = make_scope_name_attrs(ctx.scope_label, ctx.scope_name);
+    let ts = sample_timestamp(&ctx.options, ctx.format, time);
+    let created = monotonic && ctx.options.created && ctx.format == Format::OpenMetrics;
+    let attrs = &mut ctx.attr_buffer;
+    let name = MetricName {
+        name: &ctx.name,
+        quoted: ctx.quoted_name,
+    };
+
+    for (key, series) in accumulator.series::<T>() {
+        let SeriesValues::Sum(value) = series.values else {
+            continue;
+        };
+        let values = SumValues {
+            value,
+            monotonic,
+            exemplars: exemplars.remove(key).unwrap_or_default(),
+            created: created.then_some(series.start_time),
+        };
+        attrs.clear();
+        let Ok(()) = write_attrs(
+            attrs,
+            &ctx.options,
+            series.attributes.iter(),
+            scope_name_attrs.as_slice(),
+        );
+        write_sum_values(&mut ctx.f, &ctx.options, name, attrs, ts, values)?;
+    }
+    Ok(())
+}
+
+/// The values of a single sum series.
+struct SumValues<'a, T> {
+    value: T,
+    /// whether the sum is written as a counter, or as a gauge otherwise
+    monotonic: bool,
+    /// the exemplars to attach to the sample of a counter
+    exemplars: Vec<ExemplarData<'a>>,
+    /// the start time of the series, if a `_created` sample is written
+    created: Option<SystemTime>,
+}
+
+fn write_sum_values<T: FastDisplay, U: uWrite>(
+    f: &mut U,
+    options: &ConversionOptions,
+    name: MetricName,
+    attrs: &str,
+    ts: impl uDisplay + Copy,
+    values: SumValues<'_, T>,
+) -> Result<(), U::Error> {
+    let suffix = if values.monotonic && options.writes_total_suffix() {
+        "_total"
     } else {
-        for point in points {
-            attrs.clear();
-            let Ok(()) = write_attrs(attrs, point.attributes().chain(scope_name_attrs.iter()));
-            uwriteln!(
-                ctx.f,
-                "{}{{{}}} {} {}",
-                ctx.name,
-                attrs,
-                point.value().fast_display(),
-                ts,
-            )?;
+        ""
+    };
+    uwrite!(
+        f,
+        "{}{}}} {}{}",
+        name.sample(suffix, !attrs.is_empty()),
+        attrs,
+        values.value.fast_display(),
+        ts,
+    )?;
+    // OpenMetrics only allows exemplars on counters, not on gauges
+    if values.monotonic
+        && let Some(exemplar) = latest_exemplar(&values.exemplars, ..)
+    {
+        write_exemplar(f, options, exemplar)?;
+    }
+    f.write_char('\n')?;
+    if let Some(created) = values.created {
+        write_created(f, name, attrs, created, ts)?;
+    }
+    Ok(())
+}
+
+/// Write the running totals of the `idle` metric, see [DeltaAccumulator].
+fn write_idle_values<U: uWrite>(
+    ctx: &mut Context<'_, U>,
+    idle: &IdleMetric,
+) -> Result<(), U::Error> {
+    fn write_idle_metric_values<T: FastDisplay + Accumulate, U: uWrite>(
+        ctx: &mut Context<'_, U>,
+        idle: &IdleMetric,
+    ) -> Result<(), U::Error> {
+        match idle.metric.kind {
+            MetricKind::Sum { monotonic } => {
+                write_accumulated_sums::<T, U>(ctx, monotonic, idle.time, HashMap::new())
+            }
+            MetricKind::Histogram => {
+                write_accumulated_histograms::<T, U>(ctx, idle.time, HashMap::new())
+            }
+            MetricKind::ExponentialHistogram => {
+                write_accumulated_exponential_histograms::<T, U>(ctx, idle.time, HashMap::new())
+            }
         }
-        Ok(())
+    }
+    match idle.metric.number {
+        NumberKind::F64 => write_idle_metric_values::<f64, U>(ctx, idle),
+        NumberKind::U64 => write_idle_metric_values::<u64, U>(ctx, idle),
+        NumberKind::I64 => write_idle_metric_values::<i64, U>(ctx, idle),
     }
 }
 
@@ -413,17 +1494,26 @@ fn write_gauge<T: FastDisplay + Copy, U: uWrite>(
     gauge: &Gauge<T>,
 ) -> Result<(), U::Error> {
     let attrs = &mut ctx.attr_buffer;
-    let scope_name_attrs = make_scope_name_attrs(ctx.scope_name);
-    let ts = to_timestamp(gauge.time());
+    let scope_name_attrs = make_scope_name_attrs(ctx.scope_label, ctx.scope_name);
+    let ts = sample_timestamp(&ctx.options, ctx.format, gauge.time());
+    let name = MetricName {
+        name: &ctx.name,
+        quoted: ctx.quoted_name,
+    };
     let mut points: Vec<_> = gauge.data_points().collect();
     points.sort_by_cached_key(|p| hash_attrs(p.attributes()));
     for point in points {
         attrs.clear();
-        let Ok(()) = write_attrs(attrs, point.attributes().chain(scope_name_attrs.iter()));
+        let Ok(()) = write_attrs(
+            attrs,
+            &ctx.options,
+            point.attributes(),
+            scope_name_attrs.as_slice(),
+        );
         uwriteln!(
             ctx.f,
-            "{}{{{}}} {} {}",
-            ctx.name,
+            "{}{}}} {}{}",
+            name.sample("", !attrs.is_empty()),
             attrs,
             point.value().fast_display(),
             ts,
@@ -432,44 +1522,130 @@ fn write_gauge<T: FastDisplay + Copy, U: uWrite>(
     Ok(())
 }
 
-/// Makes an `otel_scope_name` attribute with the specified `scope_name` if the `otel_scope_info` feature is active.
+/// Makes an `otel_scope_name` attribute with the specified `scope_name` if the samples are labeled with it, see
+/// [Context::scope_label].
 #[inline(always)]
-fn make_scope_name_attrs(scope_name: &str) -> Option<KeyValue> {
-    if cfg!(feature = "otel_scope_info") {
+fn make_scope_name_attrs(scope_label: bool, scope_name: &str) -> Option<KeyValue> {
+    if scope_label {
         Some(KeyValue::new("otel_scope_name", scope_name.to_owned()))
     } else {
         None
     }
 }
 
-/// Write the attribute string for attrs. Does not write curly braces.
+/// Label names which the converter writes on the samples of some metric types. Attributes whose label name is reserved
+/// or one of the converter's own labels are renamed with an `exported_` prefix, like Prometheus does for scraped labels
+/// which clash with target labels.
+const RESERVED_LABELS: [&str; 2] = ["le", "quantile"];
+
+/// Write the attribute string for `attrs`, followed by the converter's own `labels`. Does not write curly braces.
 fn write_attrs<'a, I: Iterator<Item = &'a KeyValue>, U: uWrite>(
     f: &mut U,
+    options: &ConversionOptions,
     attrs: I,
+    labels: &'a [KeyValue],
 ) -> Result<(), U::Error> {
-    write_attrs_tuple(f, attrs.map(|kv| (&kv.key, &kv.value)))
+    write_attrs_tuple(f, options, attrs.map(|kv| (&kv.key, &kv.value)), labels)
 }
 
 fn write_attrs_tuple<'a, I: Iterator<Item = (&'a Key, &'a Value)>, U: uWrite>(
     f: &mut U,
+    options: &ConversionOptions,
     attrs: I,
+    labels: &'a [KeyValue],
 ) -> Result<(), U::Error> {
-    let mut first = true;
+    for (i, label) in translate_labels(options, attrs, labels).iter().enumerate() {
+        if i > 0 {
+            f.write_char(',')?;
+        }
+        label.write_to(f)?;
+    }
+    Ok(())
+}
+
+/// A label as written: the translated name and the values of all attributes translated to it.
+struct Label<'a> {
+    name: Cow<'a, str>,
+    values: Vec<&'a Value>,
+}
+
+impl Label<'_> {
+    /// Writes `name="value"`, with multiple values joined by `;`.
+    fn write_to<U: uWrite>(&self, f: &mut U) -> Result<(), U::Error> {
+        write_label_name(f, &self.name)?;
+        f.write_str("=\"")?;
+        for (i, value) in self.values.iter().enumerate() {
+            if i > 0 {
+                f.write_char(';')?;
+            }
+            write_escaped(f, &value.as_str())?;
+        }
+        f.write_char('"')
+    }
+}
 
-    let mut attrs: Vec<_> = attrs.collect();
+/// Translates `attrs` into labels sorted by name, followed by the converter's own `labels`, which are never renamed.
+fn translate_labels<'a>(
+    options: &ConversionOptions,
+    attrs: impl Iterator<Item = (&'a Key, &'a Value)>,
+    labels: &'a [KeyValue],
+) -> Vec<Label<'a>> {
+    let mut attrs: Vec<_> = attrs
+        .map(|(key, value)| (key, label_name(options, key.as_str()), value))
+        .collect();
+    if attrs.iter().any(|attr| is_reserved_label(&attr.1, labels)) {
+        let names: Vec<String> = attrs.iter().map(|attr| attr.1.to_string()).collect();
+        for attr in &mut attrs {
+            attr.1 = exported_label_name(std::mem::take(&mut attr.1), labels, &names);
+        }
+    }
+    attrs.extend(
+        labels
+            .iter()
+            .map(|kv| (&kv.key, Cow::Borrowed(kv.key.as_str()), &kv.value)),
+    );
     attrs.sort_unstable_by_key(|attr| attr.0);
 
-    for attr in attrs {
-        if !first {
-            f.write_char(',')?;
+    // Keys which collide after sanitization are written as a single label. According to the
+    // [spec](https://github.com/open-telemetry/opentelemetry-specification/blob/v1.45.0/specification/compatibility/prometheus_and_openmetrics.md#metric-attributes),
+    // the values are concatenated with `;` in the lexicographical order of the original keys, which the stable sort
+    // preserves.
+    attrs.sort_by(|a, b| a.1.cmp(&b.1));
+
+    let mut result: Vec<Label<'a>> = Vec::with_capacity(attrs.len());
+    for (_, name, value) in attrs {
+        match result.last_mut() {
+            Some(label) if label.name == name => label.values.push(value),
+            _ => result.push(Label {
+                name,
+                values: vec![value],
+            }),
         }
-        write_sanitized_name(f, attr.0.as_str())?;
-        f.write_str("=\"")?;
-        write_escaped(f, &attr.1.as_str())?;
-        f.write_char('"')?;
-        first = false;
     }
-    Ok(())
+    result
+}
+
+/// Returns `true` if `name` is reserved or one of the converter's own `labels`, see [RESERVED_LABELS].
+fn is_reserved_label(name: &str, labels: &[KeyValue]) -> bool {
+    RESERVED_LABELS.contains(&name) || labels.iter().any(|kv| kv.key.as_str() == name)
+}
+
+/// Renames the label `name` of an attribute if it is reserved, see [RESERVED_LABELS]. The `exported_` prefix is
+/// repeated until the name differs from the other attribute label `names`, e.g. `le` becomes `exported_exported_le`
+/// if there is an `exported_le` attribute as well.
+fn exported_label_name<'k>(
+    name: Cow<'k, str>,
+    labels: &[KeyValue],
+    names: &[impl AsRef<str>],
+) -> Cow<'k, str> {
+    if !is_reserved_label(&name, labels) {
+        return name;
+    }
+    let mut exported = format!("exported_{name}");
+    while is_reserved_label(&exported, labels) || names.iter().any(|n| n.as_ref() == exported) {
+        exported.insert_str(0, "exported_");
+    }
+    Cow::Owned(exported)
 }
 
 /// Calculates a hash of the [KeyValue] pairs which is invariant under reordering of the [KeyValue]s within the [Iterator].
@@ -487,19 +1663,37 @@ fn hash_attrs<'a, I: Iterator<Item = &'a KeyValue>>(attrs: I) -> u64 {
 /// Writes to `f` the contents of `value` as an escaped string. Does not put quotes around the value.
 /// The chars to escape are `\`, `"` and `\n`.
 fn write_escaped<U: uWrite>(f: &mut U, value: &str) -> Result<(), U::Error> {
+    write_escaped_chars::<true, U>(f, value)
+}
+
+/// Writes to `f` the contents of `value` as an escaped Prometheus help text.
+/// The chars to escape are `\` and `\n`.
+fn write_escaped_help<U: uWrite>(f: &mut U, value: &str) -> Result<(), U::Error> {
+    write_escaped_chars::<false, U>(f, value)
+}
+
+/// Writes to `f` the contents of `value`, escaping `\`, `\n` and, if `QUOTES` is set, `"`.
+fn write_escaped_chars<const QUOTES: bool, U: uWrite>(
+    f: &mut U,
+    value: &str,
+) -> Result<(), U::Error> {
     #[inline]
-    fn next_escape_char(bytes: &[u8]) -> Option<usize> {
+    fn next_escape_char<const QUOTES: bool>(bytes: &[u8]) -> Option<usize> {
         #[cfg(feature = "fast")]
-        return memchr::memchr3(b'\\', b'"', b'\n', bytes);
+        return if QUOTES {
+            memchr::memchr3(b'\\', b'"', b'\n', bytes)
+        } else {
+            memchr::memchr2(b'\\', b'\n', bytes)
+        };
         #[cfg(not(feature = "fast"))]
         bytes
             .iter()
-            .position(|&byte| byte == b'\\' || byte == b'"' || byte == b'\n')
+            .position(|&byte| byte == b'\\' || (QUOTES && byte == b'"') || byte == b'\n')
     }
 
     let mut bytes = value.as_bytes();
 
-    while let Some(next_escape) = next_escape_char(bytes) {
+    while let Some(next_escape) = next_escape_char::<QUOTES>(bytes) {
         let (head, tail) = bytes.split_at(next_escape);
         f.write_str(str::from_utf8(head).expect("escapable chars should be on a char boundary"))?;
         match tail[0] {
@@ -539,11 +1733,212 @@ fn write_sanitized_name<U: uWrite>(f: &mut U, name: &str) -> Result<(), U::Error
     Ok(())
 }
 
+/// Translates the attribute `key` into a label name, escaped if selected by `options`. Keys starting with `__`, which
+/// are reserved by Prometheus, are prefixed with `key` whether escaped or not, see [sanitize_label_name].
+fn label_name<'k>(options: &ConversionOptions, key: &'k str) -> Cow<'k, str> {
+    if options.escapes_names() {
+        sanitize_label_name(key)
+    } else if key.starts_with("__") {
+        Cow::Owned(format!("key{key}"))
+    } else {
+        Cow::Borrowed(key)
+    }
+}
+
+/// Write the translated label `name`, in the quoted syntax if it is not a valid label name otherwise.
+fn write_label_name<U: uWrite>(f: &mut U, name: &str) -> Result<(), U::Error> {
+    if is_legacy_label_name(name) {
+        f.write_str(name)
+    } else {
+        f.write_char('"')?;
+        write_escaped(f, name)?;
+        f.write_char('"')
+    }
+}
+
+/// Sanitizes `name` into a label name according to the
+/// [spec](https://github.com/open-telemetry/opentelemetry-specification/blob/v1.45.0/specification/compatibility/prometheus_and_openmetrics.md#metric-attributes):
+/// illegal characters are replaced with a single underscore and names starting with a digit are prefixed with `key_`.
+/// Names starting with `__`, which are reserved by Prometheus, are prefixed with `key`.
+fn sanitize_label_name(name: &str) -> Cow<'_, str> {
+    if is_legacy_label_name(name) && !name.contains("__") {
+        return Cow::Borrowed(name);
+    }
+    let mut label = String::with_capacity(name.len() + 4);
+    if name.starts_with(|c: char| c.is_ascii_digit()) {
+        label.push_str("key_");
+    } else if name.starts_with("__") {
+        label.push_str("key");
+    }
+    for c in name.chars() {
+        if c.is_ascii_alphanumeric() {
+            label.push(c);
+        } else if !label.ends_with('_') {
+            label.push('_');
+        }
+    }
+    Cow::Owned(label)
+}
+
+/// Returns `true` if `name` is a valid metric name without the quoted syntax, i.e. matches `[a-zA-Z_:][a-zA-Z0-9_:]*`.
+fn is_legacy_metric_name(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_' || c == ':')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == ':')
+}
+
+/// Returns `true` if `name` is a valid label name without the quoted syntax, i.e. matches `[a-zA-Z_][a-zA-Z0-9_]*`.
+fn is_legacy_label_name(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// The translated name of the current metric, see [Context::name].
+#[derive(Clone, Copy)]
+struct MetricName<'a> {
+    name: &'a str,
+    quoted: bool,
+}
+
+impl<'a> MetricName<'a> {
+    /// Get a [uDisplay] implementation for the name of the metric family with `suffix`, as written in metadata.
+    fn family(self, suffix: &'a str) -> impl uDisplay + 'a {
+        SampleName {
+            name: self,
+            suffix,
+            braces: false,
+            labels: false,
+        }
+    }
+
+    /// Get a [uDisplay] implementation for the name of a sample with `suffix`, including the opening brace of the
+    /// label set. If the label set is not empty, as indicated by `labels`, the name is followed by a separator.
+    fn sample(self, suffix: &'a str, labels: bool) -> impl uDisplay + 'a {
+        SampleName {
+            name: self,
+            suffix,
+            braces: true,
+            labels,
+        }
+    }
+}
+
+/// A [uDisplay] implementation for a metric or sample name, see [MetricName].
+struct SampleName<'a> {
+    name: MetricName<'a>,
+    suffix: &'a str,
+    braces: bool,
+    labels: bool,
+}
+
+impl uDisplay for SampleName<'_> {
+    fn fmt<W>(&self, f: &mut ufmt::Formatter<'_, W>) -> Result<(), W::Error>
+    where
+        W: uWrite + ?Sized,
+    {
+        if !self.name.quoted {
+            f.write_str(self.name.name)?;
+            f.write_str(self.suffix)?;
+            if self.braces {
+                f.write_char('{')?;
+            }
+            return Ok(());
+        }
+
+        // The quoted name is the first entry of the label set: {"name",label="value"}
+        if self.braces {
+            f.write_char('{')?;
+        }
+        f.write_char('"')?;
+        let mut rest = self.name.name;
+        while let Some(next_escape) = rest.find(['\\', '"', '\n']) {
+            f.write_str(&rest[..next_escape])?;
+            f.write_str(match rest.as_bytes()[next_escape] {
+                b'\\' => "\\\\",
+                b'"' => "\\\"",
+                _ => "\\n",
+            })?;
+            rest = &rest[next_escape + 1..];
+        }
+        f.write_str(rest)?;
+        f.write_str(self.suffix)?;
+        f.write_char('"')?;
+        if self.labels {
+            f.write_char(',')?;
+        }
+        Ok(())
+    }
+}
+
+/// Get a [uDisplay] implementation for the timestamp of a sample at `time` in `format`, including the separating
+/// space, in the [TimestampMode] of `options`.
+fn sample_timestamp(
+    options: &ConversionOptions,
+    format: Format,
+    time: SystemTime,
+) -> impl uDisplay + Copy {
+    #[derive(Clone, Copy)]
+    enum SampleTimestamp {
+        None,
+        Seconds(f64),
+        /// Milliseconds, written as seconds with three decimals
+        SecondsMillis(u64),
+        Millis(i64),
+    }
+
+    impl uDisplay for SampleTimestamp {
+        fn fmt<W>(&self, f: &mut ufmt::Formatter<'_, W>) -> Result<(), W::Error>
+        where
+            W: uWrite + ?Sized,
+        {
+            match *self {
+                SampleTimestamp::None => Ok(()),
+                SampleTimestamp::Seconds(secs) => {
+                    f.write_char(' ')?;
+                    secs.fast_display().fmt(f)
+                }
+                SampleTimestamp::SecondsMillis(millis) => {
+                    f.write_char(' ')?;
+                    (millis / 1000).fast_display().fmt(f)?;
+                    f.write_char('.')?;
+                    let frac = millis % 1000;
+                    for digit in [frac / 100, frac / 10 % 10, frac % 10] {
+                        f.write_char(char::from(b'0' + digit as u8))?;
+                    }
+                    Ok(())
+                }
+                SampleTimestamp::Millis(millis) => {
+                    f.write_char(' ')?;
+                    millis.fast_display().fmt(f)
+                }
+            }
+        }
+    }
+
+    let since_epoch = time
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default();
+    match (options.timestamps, format) {
+        (TimestampMode::None, _) => SampleTimestamp::None,
+        (TimestampMode::Seconds, Format::OpenMetrics) => {
+            SampleTimestamp::Seconds(since_epoch.as_secs_f64())
+        }
+        (TimestampMode::Milliseconds, Format::OpenMetrics) => {
+            SampleTimestamp::SecondsMillis(since_epoch.as_millis() as u64)
+        }
+        (TimestampMode::Seconds | TimestampMode::Milliseconds, Format::Prometheus) => {
+            SampleTimestamp::Millis(since_epoch.as_millis() as i64)
+        }
+    }
+}
+
 /// Get a [Display] implementation which shows [SystemTime] as a unix timestamp in float seconds.
-fn to_timestamp(time: SystemTime) -> impl uDisplay {
+/// Times before the unix epoch are rejected by [extract_type_unit_and_name] and shown as `0`.
+fn to_timestamp(time: SystemTime) -> impl uDisplay + Copy {
     let ts = time
         .duration_since(SystemTime::UNIX_EPOCH)
-        .expect("Time went backwards")
+        .unwrap_or_default()
         .as_secs_f64();
     ts.fast_display()
 }
@@ -0,0 +1,500 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
+use std::time::SystemTime;
+
+use opentelemetry::KeyValue;
+use opentelemetry_sdk::metrics::Temporality;
+use opentelemetry_sdk::metrics::data::{
+    AggregatedMetrics, ExponentialHistogramDataPoint, HistogramDataPoint, Metric, MetricData,
+    ResourceMetrics, SumDataPoint,
+};
+
+use super::{
+    ConversionError, ConversionOptions, ExponentialBuckets, Format, get_times, hash_attrs,
+    write_resource_metrics,
+};
+
+/// A stateful converter which keeps running totals of metrics with [Delta](opentelemetry_sdk::metrics::Temporality::Delta)
+/// temporality, so that they can be written as cumulative OpenMetrics counters, gauges and histograms.
+///
+/// Series are identified by scope name, metric name and attribute set. The `_created` time of a series is the start
+/// time of the first delta seen for it and stays stable across conversions.
+/// Every accumulated series is written in every conversion, including series and metrics without data points in it,
+/// which the SDK leaves out when there were no measurements in the interval.
+/// Metrics with cumulative temporality are written as-is.
+#[derive(Debug, Default)]
+pub struct DeltaAccumulator {
+    /// the key of the current metric, see [DeltaAccumulator::begin_metric]
+    metric: String,
+    /// the metadata of the accumulated metrics, by metric key
+    metrics: HashMap<String, AccumulatedMetric>,
+    f64_series: HashMap<String, SeriesMap<f64>>,
+    u64_series: HashMap<String, SeriesMap<u64>>,
+    i64_series: HashMap<String, SeriesMap<i64>>,
+}
+
+/// The series of a metric, by series key.
+type SeriesMap<T> = HashMap<String, Series<T>>;
+
+impl DeltaAccumulator {
+    /// Adds the deltas in `metrics` to the running totals and writes the totals into `f` in OpenMetrics text format.
+    pub fn write_as_openmetrics(
+        &mut self,
+        metrics: &ResourceMetrics,
+        f: &mut impl Write,
+    ) -> Result<(), ConversionError> {
+        self.write_as_openmetrics_with(metrics, &ConversionOptions::default(), f)
+    }
+
+    /// Adds the deltas in `metrics` to the running totals and writes the totals into `f` in OpenMetrics text format,
+    /// as configured by `options`.
+    pub fn write_as_openmetrics_with(
+        &mut self,
+        metrics: &ResourceMetrics,
+        options: &ConversionOptions,
+        f: &mut impl Write,
+    ) -> Result<(), ConversionError> {
+        write_resource_metrics(metrics, options, Format::OpenMetrics, Some(self), None, f)
+    }
+
+    /// Selects `metric` of the scope `scope_name` for following calls to the `add_*` methods and
+    /// [DeltaAccumulator::series], and records its metadata if it is accumulated.
+    pub(crate) fn begin_metric(&mut self, scope_name: &str, metric: &Metric) {
+        self.select_metric(scope_name, metric.name());
+        let Some((kind, number)) = accumulated_kind(metric.data()) else {
+            self.remove_metric();
+            return;
+        };
+        match self.metrics.get_mut(&self.metric) {
+            Some(info) if info.kind == kind && info.number == number => {
+                if info.description != metric.description() || info.unit != metric.unit() {
+                    info.description = metric.description().to_owned();
+                    info.unit = metric.unit().to_owned();
+                }
+            }
+            _ => {
+                // The instrument changed its kind, start over
+                self.remove_metric();
+                self.metrics.insert(
+                    self.metric.clone(),
+                    AccumulatedMetric {
+                        scope: scope_name.to_owned(),
+                        name: metric.name().to_owned(),
+                        description: metric.description().to_owned(),
+                        unit: metric.unit().to_owned(),
+                        kind,
+                        number,
+                    },
+                );
+            }
+        }
+    }
+
+    /// Selects the metric `metric_name` of the scope `scope_name` without changing its metadata.
+    pub(crate) fn select_metric(&mut self, scope_name: &str, metric_name: &str) {
+        self.metric = metric_key(scope_name, metric_name);
+    }
+
+    /// Forgets the metadata and the series of the current metric.
+    fn remove_metric(&mut self) {
+        self.metrics.remove(&self.metric);
+        self.f64_series.remove(&self.metric);
+        self.u64_series.remove(&self.metric);
+        self.i64_series.remove(&self.metric);
+    }
+
+    /// Gets the accumulated metrics which have no data points in `metrics`, to be written with their running totals
+    /// at the latest collection time of `metrics`.
+    pub(crate) fn idle_metrics(&self, metrics: &ResourceMetrics) -> Vec<IdleMetric> {
+        let mut present = HashSet::new();
+        let mut time = None;
+        for scope in metrics.scope_metrics() {
+            for metric in scope.metrics() {
+                present.insert(metric_key(scope.scope().name(), metric.name()));
+                time = time.max(get_times(metric.data())[0]);
+            }
+        }
+        let time = time.unwrap_or_else(SystemTime::now);
+        self.metrics
+            .iter()
+            .filter(|(key, _)| !present.contains(*key))
+            .map(|(_, metric)| IdleMetric {
+                metric: metric.clone(),
+                time,
+            })
+            .collect()
+    }
+
+    /// Adds the delta sum `point`, which started at `start_time`, to the running total of its series.
+    /// Returns the key of the series.
+    pub(crate) fn add_sum<T: Accumulate>(
+        &mut self,
+        point: &SumDataPoint<T>,
+        start_time: SystemTime,
+    ) -> String {
+        let key = series_key(point.attributes());
+        let delta = point.value();
+        let series = self
+            .series_mut::<T>()
+            .entry(key.clone())
+            .or_insert_with(|| Series {
+                attributes: point.attributes().cloned().collect(),
+                start_time,
+                values: SeriesValues::Sum(T::default()),
+            });
+        match &mut series.values {
+            SeriesValues::Sum(total) => *total = total.wrapping_add(delta),
+            _ => {
+                // The instrument changed its kind, start over
+                series.start_time = start_time;
+                series.values = SeriesValues::Sum(delta);
+            }
+        }
+        key
+    }
+
+    /// Adds the delta histogram `point`, which started at `start_time`, to the running totals of its series.
+    /// Returns the key of the series.
+    pub(crate) fn add_histogram<T: Accumulate>(
+        &mut self,
+        point: &HistogramDataPoint<T>,
+        start_time: SystemTime,
+    ) -> String {
+        let key = series_key(point.attributes());
+        let series = self
+            .series_mut::<T>()
+            .entry(key.clone())
+            .or_insert_with(|| Series {
+                attributes: point.attributes().cloned().collect(),
+                start_time,
+                values: SeriesValues::Histogram(HistogramTotals::empty(point)),
+            });
+        match &mut series.values {
+            SeriesValues::Histogram(totals) if totals.bounds.iter().copied().eq(point.bounds()) => {
+                totals.add(point);
+            }
+            _ => {
+                // The instrument changed its kind or bucket boundaries, start over
+                let mut totals = HistogramTotals::empty(point);
+                totals.add(point);
+                series.start_time = start_time;
+                series.values = SeriesValues::Histogram(totals);
+            }
+        }
+        key
+    }
+
+    /// Adds the delta exponential histogram `point`, which started at `start_time`, to the running totals of its
+    /// series. Returns the key of the series.
+    pub(crate) fn add_exponential_histogram<T: Accumulate>(
+        &mut self,
+        point: &ExponentialHistogramDataPoint<T>,
+        start_time: SystemTime,
+    ) -> String {
+        let key = series_key(point.attributes());
+        let series = self
+            .series_mut::<T>()
+            .entry(key.clone())
+            .or_insert_with(|| Series {
+                attributes: point.attributes().cloned().collect(),
+                start_time,
+                values: SeriesValues::ExponentialHistogram(ExponentialTotals::empty(point)),
+            });
+        match &mut series.values {
+            SeriesValues::ExponentialHistogram(totals) => totals.add(point),
+            _ => {
+                // The instrument changed its kind, start over
+                let mut totals = ExponentialTotals::empty(point);
+                totals.add(point);
+                series.start_time = start_time;
+                series.values = SeriesValues::ExponentialHistogram(totals);
+            }
+        }
+        key
+    }
+
+    /// Gets the keys and the running totals of all series of the current metric, ordered like the data points of a
+    /// conversion without accumulation.
+    pub(crate) fn series<T: Accumulate>(&self) -> Vec<(&str, &Series<T>)> {
+        let Some(series) = T::series(self).get(&self.metric) else {
+            return Vec::new();
+        };
+        let mut series: Vec<_> = series.iter().map(|(key, s)| (key.as_str(), s)).collect();
+        series.sort_by_cached_key(|(_, s)| hash_attrs(s.attributes.iter()));
+        series
+    }
+
+    fn series_mut<T: Accumulate>(&mut self) -> &mut SeriesMap<T> {
+        let metric = self.metric.clone();
+        T::series_mut(self).entry(metric).or_default()
+    }
+}
+
+/// Builds the key identifying the metric `metric_name` of the scope `scope_name`.
+fn metric_key(scope_name: &str, metric_name: &str) -> String {
+    format!("{scope_name}\0{metric_name}")
+}
+
+/// Builds the key identifying the series with `attrs` within its metric.
+fn series_key<'a>(attrs: impl Iterator<Item = &'a KeyValue>) -> String {
+    let mut attrs: Vec<_> = attrs.collect();
+    attrs.sort_unstable_by(|a, b| a.key.cmp(&b.key));
+
+    let mut key = String::new();
+    for kv in attrs {
+        key.push('\0');
+        key.push_str(kv.key.as_str());
+        key.push('\0');
+        key.push_str(&kv.value.as_str());
+    }
+    key
+}
+
+/// Gets the kind and number type of a metric which is accumulated, i.e. a sum or histogram with delta temporality.
+fn accumulated_kind(metric: &AggregatedMetrics) -> Option<(MetricKind, NumberKind)> {
+    fn get_metric_data_kind<T>(metric_data: &MetricData<T>) -> Option<MetricKind> {
+        match metric_data {
+            MetricData::Sum(sum) if sum.temporality() == Temporality::Delta => {
+                Some(MetricKind::Sum {
+                    monotonic: sum.is_monotonic(),
+                })
+            }
+            MetricData::Histogram(hist) if hist.temporality() == Temporality::Delta => {
+                Some(MetricKind::Histogram)
+            }
+            MetricData::ExponentialHistogram(hist) if hist.temporality() == Temporality::Delta => {
+                Some(MetricKind::ExponentialHistogram)
+            }
+            _ => None,
+        }
+    }
+    match metric {
+        AggregatedMetrics::F64(metric_data) => {
+            Some((get_metric_data_kind(metric_data)?, NumberKind::F64))
+        }
+        AggregatedMetrics::U64(metric_data) => {
+            Some((get_metric_data_kind(metric_data)?, NumberKind::U64))
+        }
+        AggregatedMetrics::I64(metric_data) => {
+            Some((get_metric_data_kind(metric_data)?, NumberKind::I64))
+        }
+    }
+}
+
+/// The metadata of an accumulated metric, kept to write its series while it has no data points.
+#[derive(Debug, Clone)]
+pub(crate) struct AccumulatedMetric {
+    pub(crate) scope: String,
+    pub(crate) name: String,
+    pub(crate) description: String,
+    pub(crate) unit: String,
+    pub(crate) kind: MetricKind,
+    pub(crate) number: NumberKind,
+}
+
+/// An accumulated metric without data points in the current conversion.
+#[derive(Debug)]
+pub(crate) struct IdleMetric {
+    pub(crate) metric: AccumulatedMetric,
+    /// the collection time of the current conversion
+    pub(crate) time: SystemTime,
+}
+
+/// The kinds of accumulated metrics.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum MetricKind {
+    /// a sum, written as a counter if monotonic and as a gauge otherwise
+    Sum {
+        monotonic: bool,
+    },
+    Histogram,
+    /// an exponential histogram, written as a classic histogram
+    ExponentialHistogram,
+}
+
+impl MetricKind {
+    /// Gets the OpenMetrics metric type of this kind.
+    pub(crate) fn typ(self) -> &'static str {
+        match self {
+            MetricKind::Sum { monotonic: true } => "counter",
+            MetricKind::Sum { monotonic: false } => "gauge",
+            MetricKind::Histogram | MetricKind::ExponentialHistogram => "histogram",
+        }
+    }
+}
+
+/// The number types of accumulated metrics, see [Accumulate].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum NumberKind {
+    F64,
+    U64,
+    I64,
+}
+
+/// The running totals of a series.
+#[derive(Debug)]
+pub(crate) struct Series<T> {
+    pub(crate) attributes: Vec<KeyValue>,
+    /// the start time of the first delta of the series
+    pub(crate) start_time: SystemTime,
+    pub(crate) values: SeriesValues<T>,
+}
+
+#[derive(Debug)]
+pub(crate) enum SeriesValues<T> {
+    Sum(T),
+    Histogram(HistogramTotals<T>),
+    ExponentialHistogram(ExponentialTotals<T>),
+}
+
+/// The running totals of a histogram series.
+#[derive(Debug)]
+pub(crate) struct HistogramTotals<T> {
+    pub(crate) count: u64,
+    pub(crate) sum: T,
+    pub(crate) min: Option<T>,
+    pub(crate) max: Option<T>,
+    pub(crate) bounds: Vec<f64>,
+    pub(crate) bucket_counts: Vec<u64>,
+}
+
+impl<T: Accumulate> HistogramTotals<T> {
+    fn empty(point: &HistogramDataPoint<T>) -> Self {
+        let bounds: Vec<f64> = point.bounds().collect();
+        HistogramTotals {
+            count: 0,
+            sum: T::default(),
+            min: None,
+            max: None,
+            bucket_counts: vec![0; point.bucket_counts().count()],
+            bounds,
+        }
+    }
+
+    fn add(&mut self, point: &HistogramDataPoint<T>) {
+        self.count = self.count.wrapping_add(point.count());
+        self.sum = self.sum.wrapping_add(point.sum());
+        self.min = min(self.min, point.min());
+        self.max = max(self.max, point.max());
+        for (total, count) in self.bucket_counts.iter_mut().zip(point.bucket_counts()) {
+            *total = total.wrapping_add(count);
+        }
+    }
+}
+
+/// The running totals of an exponential histogram series.
+#[derive(Debug)]
+pub(crate) struct ExponentialTotals<T> {
+    pub(crate) count: u64,
+    pub(crate) sum: T,
+    pub(crate) min: Option<T>,
+    pub(crate) max: Option<T>,
+    pub(crate) zero_count: u64,
+    /// the largest zero threshold seen
+    pub(crate) zero_threshold: f64,
+    /// the bucket counts at the lowest scale seen
+    pub(crate) buckets: ExponentialBuckets,
+}
+
+impl<T: Accumulate> ExponentialTotals<T> {
+    /// The default maximum number of buckets per sign of the SDK's exponential histograms. Running totals are only
+    /// downscaled beyond it if the data points themselves have more buckets.
+    const MAX_BUCKETS: usize = 160;
+
+    fn empty(point: &ExponentialHistogramDataPoint<T>) -> Self {
+        ExponentialTotals {
+            count: 0,
+            sum: T::default(),
+            min: None,
+            max: None,
+            zero_count: 0,
+            zero_threshold: 0.0,
+            buckets: ExponentialBuckets {
+                scale: point.scale(),
+                negative_offset: 0,
+                negative_counts: Vec::new(),
+                positive_offset: 0,
+                positive_counts: Vec::new(),
+            },
+        }
+    }
+
+    fn add(&mut self, point: &ExponentialHistogramDataPoint<T>) {
+        self.count = self.count.wrapping_add(point.count() as u64);
+        self.sum = self.sum.wrapping_add(point.sum());
+        self.min = min(self.min, point.min());
+        self.max = max(self.max, point.max());
+        self.zero_count = self.zero_count.wrapping_add(point.zero_count());
+        self.zero_threshold = self.zero_threshold.max(point.zero_threshold());
+        let buckets = ExponentialBuckets::new(
+            point.scale(),
+            point.negative_bucket(),
+            point.positive_bucket(),
+            None,
+        );
+        let max_buckets = Self::MAX_BUCKETS
+            .max(buckets.negative_counts.len())
+            .max(buckets.positive_counts.len());
+        self.buckets.add(&buckets, max_buckets);
+    }
+}
+
+/// Gets the lower of two optional values.
+fn min<T: PartialOrd>(a: Option<T>, b: Option<T>) -> Option<T> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(if b < a { b } else { a }),
+        (a, b) => a.or(b),
+    }
+}
+
+/// Gets the higher of two optional values.
+fn max<T: PartialOrd>(a: Option<T>, b: Option<T>) -> Option<T> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(if b > a { b } else { a }),
+        (a, b) => a.or(b),
+    }
+}
+
+/// Number types which can be accumulated by a [DeltaAccumulator].
+pub(crate) trait Accumulate: Copy + Default + PartialOrd {
+    /// Adds `other`, wrapping around at the bounds of integers like Prometheus counters do.
+    fn wrapping_add(self, other: Self) -> Self;
+    fn series(accumulator: &DeltaAccumulator) -> &HashMap<String, SeriesMap<Self>>;
+    fn series_mut(accumulator: &mut DeltaAccumulator) -> &mut HashMap<String, SeriesMap<Self>>;
+}
+
+impl Accumulate for f64 {
+    fn wrapping_add(self, other: Self) -> Self {
+        self + other
+    }
+    fn series(accumulator: &DeltaAccumulator) -> &HashMap<String, SeriesMap<Self>> {
+        &accumulator.f64_series
+    }
+    fn series_mut(accumulator: &mut DeltaAccumulator) -> &mut HashMap<String, SeriesMap<Self>> {
+        &mut accumulator.f64_series
+    }
+}
+
+impl Accumulate for u64 {
+    fn wrapping_add(self, other: Self) -> Self {
+        u64::wrapping_add(self, other)
+    }
+    fn series(accumulator: &DeltaAccumulator) -> &HashMap<String, SeriesMap<Self>> {
+        &accumulator.u64_series
+    }
+    fn series_mut(accumulator: &mut DeltaAccumulator) -> &mut HashMap<String, SeriesMap<Self>> {
+        &mut accumulator.u64_series
+    }
+}
+
+impl Accumulate for i64 {
+    fn wrapping_add(self, other: Self) -> Self {
+        i64::wrapping_add(self, other)
+    }
+    fn series(accumulator: &DeltaAccumulator) -> &HashMap<String, SeriesMap<Self>> {
+        &accumulator.i64_series
+    }
+    fn series_mut(accumulator: &mut DeltaAccumulator) -> &mut HashMap<String, SeriesMap<Self>> {
+        &mut accumulator.i64_series
+    }
+}
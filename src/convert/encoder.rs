@@ -0,0 +1,166 @@
+use std::borrow::Cow;
+
+use opentelemetry_sdk::metrics::data::ResourceMetrics;
+use ufmt::uWrite;
+
+use super::{
+    Context, ConversionError, ConversionOptions, Family, Format, group_families, sorted_scopes,
+    write_eof, write_family, write_info_metrics,
+};
+
+/// Converts metrics lazily into chunks of text, for writers with backpressure such as chunked HTTP bodies.
+///
+/// Each call to [next_chunk](Self::next_chunk) converts metric families until the chunk holds at least `chunk_size`
+/// bytes. Metric families are never split across chunks, so a chunk can be larger than the chunk size if a single
+/// family has many data points.
+///
+/// ```
+/// # use opentelemetry_openmetrics::convert::{ConversionOptions, Encoder};
+/// # let metrics = opentelemetry_sdk::metrics::data::ResourceMetrics::default();
+/// let options = ConversionOptions::default();
+/// let mut encoder = Encoder::new(&metrics, &options, 64 * 1024);
+/// while let Some(chunk) = encoder.next_chunk()? {
+///     // send the chunk
+/// }
+/// # Ok::<(), opentelemetry_openmetrics::convert::ConversionError>(())
+/// ```
+pub struct Encoder<'a> {
+    ctx: Context<'a, ChunkBuffer>,
+    metrics: &'a ResourceMetrics,
+    families: std::vec::IntoIter<Family<'a>>,
+    state: State,
+    chunk_size: usize,
+}
+
+impl std::fmt::Debug for Encoder<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Encoder")
+            .field("state", &self.state)
+            .field("chunk_size", &self.chunk_size)
+            .finish_non_exhaustive()
+    }
+}
+
+/// The progress of an [Encoder].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    Start,
+    Metrics,
+    Done,
+}
+
+impl<'a> Encoder<'a> {
+    /// Creates an encoder for `metrics` in OpenMetrics text format, as configured by `options`.
+    pub fn new(
+        metrics: &'a ResourceMetrics,
+        options: &'a ConversionOptions,
+        chunk_size: usize,
+    ) -> Self {
+        Self::with_format(
+            metrics,
+            Cow::Borrowed(options),
+            Format::OpenMetrics,
+            chunk_size,
+        )
+    }
+
+    pub(crate) fn with_format(
+        metrics: &'a ResourceMetrics,
+        options: Cow<'a, ConversionOptions>,
+        format: Format,
+        chunk_size: usize,
+    ) -> Self {
+        Encoder {
+            ctx: Context {
+                // the buffer grows as needed if the chunk size is huge
+                f: ChunkBuffer(String::with_capacity(chunk_size.min(1 << 20))),
+                attr_buffer: String::with_capacity(256),
+                name: String::with_capacity(64),
+                quoted_name: false,
+                unit: None,
+                typ: "",
+                scope_name: "",
+                scope_label: options.scope_info,
+                options,
+                accumulator: None,
+                report: None,
+                format,
+            },
+            metrics,
+            families: Vec::new().into_iter(),
+            state: State::Start,
+            chunk_size,
+        }
+    }
+
+    /// Converts and returns the next chunk of text. Returns [None] once all metrics were written.
+    pub fn next_chunk(&mut self) -> Result<Option<&str>, ConversionError> {
+        self.ctx.f.0.clear();
+        // Every chunk holds at least one step, even with a `chunk_size` of zero
+        while self.ctx.f.0.len() < self.chunk_size.max(1) && self.state != State::Done {
+            self.step()?;
+        }
+        if self.ctx.f.0.is_empty() {
+            Ok(None)
+        } else {
+            Ok(Some(&self.ctx.f.0))
+        }
+    }
+
+    /// Writes the next piece of output: the info metrics, a single metric family or the end of the exposition.
+    fn step(&mut self) -> Result<(), ConversionError> {
+        match self.state {
+            State::Start => {
+                let scopes = sorted_scopes(self.metrics);
+                write_info_metrics(&mut self.ctx, self.metrics, &scopes)?;
+                self.families = group_families(&mut self.ctx, &scopes, &[])?.into_iter();
+                self.state = State::Metrics;
+            }
+            State::Metrics => {
+                if let Some(family) = self.families.next() {
+                    write_family(&mut self.ctx, &family)?;
+                } else {
+                    write_eof(&mut self.ctx)?;
+                    self.state = State::Done;
+                }
+            }
+            State::Done => {}
+        }
+        Ok(())
+    }
+}
+
+/// The output buffer of an [Encoder].
+struct ChunkBuffer(String);
+
+impl uWrite for ChunkBuffer {
+    type Error = std::fmt::Error;
+
+    fn write_str(&mut self, s: &str) -> Result<(), Self::Error> {
+        self.0.push_str(s);
+        Ok(())
+    }
+}
+
+/// Writes `metrics` into the async byte sink `w` in OpenMetrics text format, as configured by `options`.
+///
+/// The text is converted in chunks of about `chunk_size` bytes, each written to `w` before the next one is
+/// converted, so the memory use is bounded by the chunk size and the largest single metric family.
+/// Errors of `w` are returned as [ConversionError::Io].
+#[cfg(feature = "tokio")]
+pub async fn write_as_openmetrics_async(
+    metrics: &ResourceMetrics,
+    options: &ConversionOptions,
+    chunk_size: usize,
+    w: &mut (impl tokio::io::AsyncWrite + Unpin),
+) -> Result<(), ConversionError> {
+    use tokio::io::AsyncWriteExt;
+
+    let mut encoder = Encoder::new(metrics, options, chunk_size);
+    while let Some(chunk) = encoder.next_chunk()? {
+        w.write_all(chunk.as_bytes())
+            .await
+            .map_err(ConversionError::Io)?;
+    }
+    w.flush().await.map_err(ConversionError::Io)
+}
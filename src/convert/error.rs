@@ -0,0 +1,93 @@
+use std::fmt::{Display, Formatter};
+use std::time::SystemTime;
+
+use opentelemetry_sdk::metrics::Temporality;
+
+/// An error which occurred while converting metrics to OpenMetrics text.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum ConversionError {
+    /// The metric has a temporality which cannot be represented. Delta temporality is only supported through a
+    /// [DeltaAccumulator](super::DeltaAccumulator).
+    UnsupportedTemporality {
+        scope: String,
+        metric: String,
+        temporality: Temporality,
+    },
+    /// The metric has a timestamp before the unix epoch.
+    InvalidTimestamp {
+        scope: String,
+        metric: String,
+        time: SystemTime,
+    },
+    /// The metric is written as a metric family which a metric of another scope, or with another name, already uses
+    /// with a different type or unit, or which another metric of the same scope already uses. The metric that comes
+    /// first, ordered by scope and metric name, is kept.
+    ConflictingFamily {
+        scope: String,
+        metric: String,
+        family: String,
+    },
+    /// Writing to the output failed.
+    Write(std::fmt::Error),
+    /// Writing to the [io::Write](std::io::Write) output failed.
+    Io(std::io::Error),
+}
+
+impl Display for ConversionError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConversionError::UnsupportedTemporality {
+                scope,
+                metric,
+                temporality,
+            } => write!(
+                f,
+                "metric {metric:?} of scope {scope:?} has unsupported temporality {temporality:?}"
+            ),
+            ConversionError::InvalidTimestamp {
+                scope,
+                metric,
+                time,
+            } => write!(
+                f,
+                "metric {metric:?} of scope {scope:?} has timestamp {time:?} before the unix epoch"
+            ),
+            ConversionError::ConflictingFamily {
+                scope,
+                metric,
+                family,
+            } => write!(
+                f,
+                "metric {metric:?} of scope {scope:?} conflicts with another metric of metric family {family:?}"
+            ),
+            ConversionError::Write(err) => write!(f, "failed to write output: {err}"),
+            ConversionError::Io(err) => write!(f, "failed to write output: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for ConversionError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ConversionError::Write(err) => Some(err),
+            ConversionError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::fmt::Error> for ConversionError {
+    fn from(err: std::fmt::Error) -> Self {
+        ConversionError::Write(err)
+    }
+}
+
+impl From<ConversionError> for std::io::Error {
+    fn from(err: ConversionError) -> Self {
+        match err {
+            ConversionError::Io(err) => err,
+            err => std::io::Error::other(err),
+        }
+    }
+}
@@ -0,0 +1,155 @@
+use std::time::SystemTime;
+
+use opentelemetry::KeyValue;
+use opentelemetry_sdk::metrics::data::Exemplar;
+use ufmt::uWrite;
+
+use super::{ConversionOptions, Format, to_timestamp, translate_labels};
+use crate::format::FastDisplay;
+
+/// The maximum combined length of the label names and values of an exemplar, in characters.
+const MAX_LABEL_SET_LENGTH: usize = 128;
+
+/// The data of an [Exemplar] needed to write it, independent of the value type.
+#[derive(Debug, Clone)]
+pub(crate) struct ExemplarData<'a> {
+    pub(crate) value: f64,
+    pub(crate) time: SystemTime,
+    pub(crate) trace_id: [u8; 16],
+    pub(crate) span_id: [u8; 8],
+    pub(crate) attributes: Vec<&'a KeyValue>,
+}
+
+/// Numbers which can be the value of an exemplar.
+pub(crate) trait ExemplarValue: Copy {
+    fn to_f64(self) -> f64;
+}
+
+impl ExemplarValue for f64 {
+    fn to_f64(self) -> f64 {
+        self
+    }
+}
+
+impl ExemplarValue for u64 {
+    fn to_f64(self) -> f64 {
+        self as f64
+    }
+}
+
+impl ExemplarValue for i64 {
+    fn to_f64(self) -> f64 {
+        self as f64
+    }
+}
+
+impl<'a, T: ExemplarValue> From<&'a Exemplar<T>> for ExemplarData<'a> {
+    fn from(exemplar: &'a Exemplar<T>) -> Self {
+        ExemplarData {
+            value: exemplar.value.to_f64(),
+            time: exemplar.time(),
+            trace_id: *exemplar.trace_id(),
+            span_id: *exemplar.span_id(),
+            attributes: exemplar.filtered_attributes().collect(),
+        }
+    }
+}
+
+/// Collects `exemplars` if they are written in `format` with `options`.
+pub(crate) fn collect_exemplars<'a, T: ExemplarValue + 'a>(
+    options: &ConversionOptions,
+    format: Format,
+    exemplars: impl Iterator<Item = &'a Exemplar<T>>,
+) -> Vec<ExemplarData<'a>> {
+    if options.exemplars && format == Format::OpenMetrics {
+        exemplars.map(ExemplarData::from).collect()
+    } else {
+        Vec::new()
+    }
+}
+
+/// Returns the most recent of the exemplars whose value is in `range`.
+pub(crate) fn latest_exemplar<'e, 'a>(
+    exemplars: &'e [ExemplarData<'a>],
+    range: impl std::ops::RangeBounds<f64>,
+) -> Option<&'e ExemplarData<'a>> {
+    exemplars
+        .iter()
+        .filter(|exemplar| range.contains(&exemplar.value))
+        .max_by_key(|exemplar| exemplar.time)
+}
+
+/// Writes ` # {labels} value timestamp` for `exemplar`, to be appended to a sample line. The timestamp is the time of
+/// the exemplar in seconds, as specified by OpenMetrics, independent of the [TimestampMode](super::TimestampMode).
+///
+/// The trace and span IDs are written as labels if present, attributes translated to the same label names are dropped.
+/// Attributes are translated like those of samples and added as long as the label set stays within the spec's limit of
+/// 128 characters, the remaining attributes are dropped.
+pub(crate) fn write_exemplar<U: uWrite>(
+    f: &mut U,
+    options: &ConversionOptions,
+    exemplar: &ExemplarData,
+) -> Result<(), U::Error> {
+    f.write_str(" # {")?;
+    let mut length = 0;
+    let mut first = true;
+    let mut hex = String::with_capacity(32);
+    let mut id_labels = [""; 2];
+    for (i, (name, id)) in [
+        ("trace_id", exemplar.trace_id.as_slice()),
+        ("span_id", exemplar.span_id.as_slice()),
+    ]
+    .into_iter()
+    .enumerate()
+    {
+        if id.iter().all(|&byte| byte == 0) {
+            continue;
+        }
+        id_labels[i] = name;
+        hex.clear();
+        for byte in id {
+            const DIGITS: &[u8; 16] = b"0123456789abcdef";
+            hex.push(DIGITS[(byte >> 4) as usize] as char);
+            hex.push(DIGITS[(byte & 0xf) as usize] as char);
+        }
+        if !first {
+            f.write_char(',')?;
+        }
+        first = false;
+        length += name.len() + hex.len();
+        for x in &[name, "=\"", &hex, "\""] {
+            f.write_str(x)?;
+        }
+    }
+    let attributes = exemplar.attributes.iter().map(|kv| (&kv.key, &kv.value));
+    for label in translate_labels(options, attributes, &[]) {
+        // The IDs take precedence over attributes of the same name
+        if id_labels.contains(&label.name.as_ref()) {
+            continue;
+        }
+        let label_length = label.name.chars().count()
+            + label
+                .values
+                .iter()
+                .map(|value| value.as_str().chars().count() + 1)
+                .sum::<usize>()
+            - 1;
+        if length + label_length > MAX_LABEL_SET_LENGTH {
+            continue;
+        }
+        length += label_length;
+        if !first {
+            f.write_char(',')?;
+        }
+        first = false;
+        label.write_to(f)?;
+    }
+    f.write_str("} ")?;
+    // Exemplar timestamps are always float seconds, whatever the timestamp mode of the samples
+    ufmt::uwrite!(
+        f,
+        "{} {}",
+        exemplar.value.fast_display(),
+        to_timestamp(exemplar.time),
+    )
+}
@@ -0,0 +1,170 @@
+use std::borrow::Cow;
+use std::fmt::Write;
+
+use opentelemetry_sdk::metrics::data::ResourceMetrics;
+
+use super::{
+    ConversionError, ConversionOptions, Encoder, Format, MIME_TYPE, PROMETHEUS_MIME_TYPE,
+    write_resource_metrics,
+};
+
+/// An exposition format selected by [negotiate] from an HTTP `Accept` header.
+///
+/// Use [content_type](Self::content_type) as the `Content-Type` of the response and
+/// [write_as](Self::write_as) to produce its body.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExpositionFormat {
+    format: Format,
+    escaping: Escaping,
+}
+
+/// The escaping scheme requested by the client.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Escaping {
+    /// No scheme requested, names must be valid without the quoted syntax
+    Legacy,
+    /// A scheme other than `allow-utf-8`, answered by escaping names with underscores
+    Underscores,
+    /// UTF-8 names in the quoted syntax are accepted
+    AllowUtf8,
+}
+
+impl ExpositionFormat {
+    /// [OpenMetrics 1.0](https://github.com/prometheus/OpenMetrics/blob/v1.0.0/specification/OpenMetrics.md),
+    /// as written by [WriteOpenMetrics](super::WriteOpenMetrics).
+    pub const OPENMETRICS: Self = ExpositionFormat {
+        format: Format::OpenMetrics,
+        escaping: Escaping::Legacy,
+    };
+    /// [Prometheus 0.0.4](https://prometheus.io/docs/instrumenting/exposition_formats/#text-based-format),
+    /// as written by [WritePrometheus](super::WritePrometheus).
+    pub const PROMETHEUS: Self = ExpositionFormat {
+        format: Format::Prometheus,
+        escaping: Escaping::Legacy,
+    };
+
+    /// Returns `true` if this is the OpenMetrics format.
+    pub fn is_openmetrics(&self) -> bool {
+        self.format == Format::OpenMetrics
+    }
+
+    /// Returns the value for the `Content-Type` header of the response.
+    ///
+    /// If the client asked for `escaping=allow-utf-8`, the response announces it as well. If it asked for any other
+    /// escaping scheme, the response announces `escaping=underscores`, since metric and label names are then written
+    /// with illegal characters replaced by underscores.
+    pub fn content_type(&self) -> &'static str {
+        match (self.format, self.escaping) {
+            (Format::OpenMetrics, Escaping::Legacy) => MIME_TYPE,
+            (Format::OpenMetrics, Escaping::Underscores) => {
+                "application/openmetrics-text; version=1.0.0; charset=utf-8; escaping=underscores"
+            }
+            (Format::OpenMetrics, Escaping::AllowUtf8) => {
+                "application/openmetrics-text; version=1.0.0; charset=utf-8; escaping=allow-utf-8"
+            }
+            (Format::Prometheus, Escaping::Legacy) => PROMETHEUS_MIME_TYPE,
+            (Format::Prometheus, Escaping::Underscores) => {
+                "text/plain; version=0.0.4; charset=utf-8; escaping=underscores"
+            }
+            (Format::Prometheus, Escaping::AllowUtf8) => {
+                "text/plain; version=0.0.4; charset=utf-8; escaping=allow-utf-8"
+            }
+        }
+    }
+
+    /// Adjusts `options` to the names accepted by the client.
+    fn options<'a>(&self, options: &'a ConversionOptions) -> Cow<'a, ConversionOptions> {
+        if self.escaping == Escaping::AllowUtf8 || options.escapes_names() {
+            Cow::Borrowed(options)
+        } else {
+            let mut options = options.clone();
+            options.legacy_names = true;
+            Cow::Owned(options)
+        }
+    }
+
+    /// Writes `metrics` into `f` in this format, as configured by `options`.
+    ///
+    /// UTF-8 names are escaped with underscores unless the client accepts them, see
+    /// [TranslationStrategy](super::TranslationStrategy).
+    pub fn write_as(
+        &self,
+        metrics: &ResourceMetrics,
+        options: &ConversionOptions,
+        f: &mut impl Write,
+    ) -> Result<(), ConversionError> {
+        let options = self.options(options);
+        write_resource_metrics(metrics, &options, self.format, None, None, f)
+    }
+
+    /// Creates an [Encoder] which converts `metrics` in this format in chunks of about `chunk_size` bytes.
+    pub fn encoder<'a>(
+        &self,
+        metrics: &'a ResourceMetrics,
+        options: &'a ConversionOptions,
+        chunk_size: usize,
+    ) -> Encoder<'a> {
+        Encoder::with_format(metrics, self.options(options), self.format, chunk_size)
+    }
+}
+
+/// Selects the best supported exposition format for the given value of an HTTP `Accept` header.
+///
+/// Media ranges are ranked by their `q` parameter, ties are broken by their order in the header. Wildcards and
+/// `text/plain` select the Prometheus format, which is also the fallback if no media range is supported.
+/// OpenMetrics is selected by `application/openmetrics-text` in version `1.0.0` or `0.0.1`.
+pub fn negotiate(accept: &str) -> ExpositionFormat {
+    let mut best: Option<(f32, ExpositionFormat)> = None;
+    for range in accept.split(',') {
+        let Some((quality, format)) = parse_media_range(range) else {
+            continue;
+        };
+        if quality > 0.0 && best.is_none_or(|(best_quality, _)| quality > best_quality) {
+            best = Some((quality, format));
+        }
+    }
+    best.map_or(ExpositionFormat::PROMETHEUS, |(_, format)| format)
+}
+
+/// Parses a single media range of an `Accept` header into its quality and format.
+/// Returns [None] if the media range is not supported.
+fn parse_media_range(range: &str) -> Option<(f32, ExpositionFormat)> {
+    let mut parts = range.split(';');
+    let media_type = parts.next()?.trim();
+
+    let mut quality = 1.0;
+    let mut version = None;
+    let mut escaping = Escaping::Legacy;
+    for param in parts {
+        let Some((key, value)) = param.split_once('=') else {
+            continue;
+        };
+        let value = value.trim().trim_matches('"');
+        match key.trim().to_ascii_lowercase().as_str() {
+            "q" => quality = value.parse().ok()?,
+            "version" => version = Some(value),
+            "escaping" if value == "allow-utf-8" => escaping = Escaping::AllowUtf8,
+            "escaping" => escaping = Escaping::Underscores,
+            _ => {}
+        }
+    }
+
+    let format = if media_type.eq_ignore_ascii_case("application/openmetrics-text") {
+        match version {
+            None | Some("1.0.0") | Some("0.0.1") => Format::OpenMetrics,
+            Some(_) => return None,
+        }
+    } else if media_type.eq_ignore_ascii_case("text/plain") {
+        match version {
+            None | Some("0.0.4") => Format::Prometheus,
+            Some(_) => return None,
+        }
+    } else if media_type == "*/*" || media_type.eq_ignore_ascii_case("text/*") {
+        Format::Prometheus
+    } else if media_type.eq_ignore_ascii_case("application/*") {
+        Format::OpenMetrics
+    } else {
+        return None;
+    };
+    Some((quality, ExpositionFormat { format, escaping }))
+}
@@ -0,0 +1,209 @@
+/// The lowest limit of [ConversionOptions::with_max_exponential_buckets] which can always be met.
+const MIN_EXPONENTIAL_BUCKETS: usize = 7;
+
+/// Options to tune how metrics are converted to OpenMetrics text.
+///
+/// The [Default] options produce the same output as [WriteOpenMetrics::write_as_openmetrics](super::WriteOpenMetrics::write_as_openmetrics).
+/// The cargo features `otel_scope_info` and `histogram-min-max` only select the defaults of the corresponding options.
+#[derive(Debug, Clone)]
+pub struct ConversionOptions {
+    pub(crate) max_exponential_buckets: Option<usize>,
+    pub(crate) scope_info: bool,
+    pub(crate) target_info: bool,
+    pub(crate) unit_suffixes: bool,
+    pub(crate) total_suffix: bool,
+    pub(crate) histogram_min_max: bool,
+    pub(crate) timestamps: TimestampMode,
+    pub(crate) exemplars: bool,
+    pub(crate) created: bool,
+    pub(crate) lenient: bool,
+    pub(crate) translation: TranslationStrategy,
+    /// set by content negotiation if the client does not accept UTF-8 names
+    pub(crate) legacy_names: bool,
+}
+
+impl Default for ConversionOptions {
+    fn default() -> Self {
+        ConversionOptions {
+            max_exponential_buckets: None,
+            scope_info: cfg!(feature = "otel_scope_info"),
+            target_info: cfg!(feature = "otel_scope_info"),
+            unit_suffixes: true,
+            total_suffix: true,
+            histogram_min_max: cfg!(feature = "histogram-min-max"),
+            timestamps: TimestampMode::Seconds,
+            exemplars: true,
+            created: true,
+            lenient: false,
+            translation: TranslationStrategy::UnderscoreEscapingWithSuffixes,
+            legacy_names: false,
+        }
+    }
+}
+
+impl ConversionOptions {
+    /// Limits the number of finite `_bucket` lines written for each exponential histogram data point.
+    ///
+    /// Exponential histograms are written as classic histograms. If a data point has more buckets than `max_buckets`,
+    /// adjacent buckets are merged pairwise (lowering the scale) until it fits. The zero bucket counts towards the limit.
+    ///
+    /// Buckets are not merged beyond the lowest scale of `-10`, at which a data point still spans up to three buckets
+    /// per sign. Limits below `7` are therefore raised to `7`.
+    pub fn with_max_exponential_buckets(mut self, max_buckets: usize) -> Self {
+        self.max_exponential_buckets = Some(max_buckets.max(MIN_EXPONENTIAL_BUCKETS));
+        self
+    }
+
+    /// Enables the `otel_scope_info` metric and the `otel_scope_name` label on every sample. Samples of metric families
+    /// merged from several scopes always have the `otel_scope_name` label, so that their series stay distinct.
+    ///
+    /// Defaults to `true` if the `otel_scope_info` feature is enabled.
+    pub fn with_scope_info(mut self, enabled: bool) -> Self {
+        self.scope_info = enabled;
+        self
+    }
+
+    /// Enables the `target_info` metric holding the resource attributes.
+    ///
+    /// Defaults to `true` if the `otel_scope_info` feature is enabled.
+    pub fn with_target_info(mut self, enabled: bool) -> Self {
+        self.target_info = enabled;
+        self
+    }
+
+    /// Enables appending the unit (e.g. `_seconds`) to metric names. Without unit suffixes, no `# UNIT` metadata is
+    /// written either, since OpenMetrics requires the unit to be a suffix of the metric name.
+    /// Ignored with [TranslationStrategy::NoTranslation].
+    ///
+    /// Defaults to `true`.
+    pub fn with_unit_suffixes(mut self, enabled: bool) -> Self {
+        self.unit_suffixes = enabled;
+        self
+    }
+
+    /// Enables the `_total` suffix on counter samples. Note that OpenMetrics requires this suffix, so only disable
+    /// it for consumers which do not expect it. Ignored with [TranslationStrategy::NoTranslation].
+    ///
+    /// Defaults to `true`.
+    pub fn with_total_suffix(mut self, enabled: bool) -> Self {
+        self.total_suffix = enabled;
+        self
+    }
+
+    /// Enables the non-standard `_min` and `_max` samples of histograms.
+    ///
+    /// Defaults to `true` if the `histogram-min-max` feature is enabled.
+    pub fn with_histogram_min_max(mut self, enabled: bool) -> Self {
+        self.histogram_min_max = enabled;
+        self
+    }
+
+    /// Enables writing the collection time as timestamp of every sample, in [TimestampMode::Seconds].
+    ///
+    /// Defaults to `true`.
+    pub fn with_timestamps(mut self, enabled: bool) -> Self {
+        self.timestamps = if enabled {
+            TimestampMode::Seconds
+        } else {
+            TimestampMode::None
+        };
+        self
+    }
+
+    /// Sets how the collection time is written as timestamp of samples. Exemplars always carry their own time in
+    /// seconds, as specified by OpenMetrics.
+    ///
+    /// Defaults to [TimestampMode::Seconds].
+    pub fn with_timestamp_mode(mut self, mode: TimestampMode) -> Self {
+        self.timestamps = mode;
+        self
+    }
+
+    /// Enables writing the exemplars of counters and histogram buckets. Exemplars are never written in the
+    /// Prometheus text format, which does not support them.
+    ///
+    /// Defaults to `true`.
+    pub fn with_exemplars(mut self, enabled: bool) -> Self {
+        self.exemplars = enabled;
+        self
+    }
+
+    /// Enables writing a `_created` sample with the start time of every counter and histogram series.
+    /// `_created` samples are never written in the Prometheus text format, which does not support them.
+    ///
+    /// Defaults to `true`.
+    pub fn with_created(mut self, enabled: bool) -> Self {
+        self.created = enabled;
+        self
+    }
+
+    /// Enables the lenient mode, in which metrics that cannot be converted are skipped instead of failing the
+    /// whole conversion with a [ConversionError](super::ConversionError). Errors of the output are never skipped.
+    ///
+    /// Defaults to `false`.
+    pub fn with_lenient(mut self, enabled: bool) -> Self {
+        self.lenient = enabled;
+        self
+    }
+
+    /// Sets how OpenTelemetry metric and attribute names are translated to metric and label names.
+    ///
+    /// Defaults to [TranslationStrategy::UnderscoreEscapingWithSuffixes].
+    pub fn with_translation_strategy(mut self, strategy: TranslationStrategy) -> Self {
+        self.translation = strategy;
+        self
+    }
+
+    /// Returns `true` if names are written with illegal characters replaced by underscores.
+    pub(crate) fn escapes_names(&self) -> bool {
+        self.translation == TranslationStrategy::UnderscoreEscapingWithSuffixes || self.legacy_names
+    }
+
+    /// Returns `true` if unit suffixes are appended to metric names.
+    pub(crate) fn writes_unit_suffixes(&self) -> bool {
+        self.unit_suffixes && self.translation != TranslationStrategy::NoTranslation
+    }
+
+    /// Returns `true` if the `_total` suffix is appended to counter samples.
+    pub(crate) fn writes_total_suffix(&self) -> bool {
+        self.total_suffix && self.translation != TranslationStrategy::NoTranslation
+    }
+}
+
+/// How OpenTelemetry names are translated, see [ConversionOptions::with_translation_strategy].
+///
+/// Names which are not valid in the classic `[a-zA-Z_:][a-zA-Z0-9_:]*` syntax are written in the quoted syntax of
+/// Prometheus 3, e.g. `{"http.server.request.duration_count","http.method"="GET"} 3`. When writing through an
+/// [ExpositionFormat](super::ExpositionFormat) selected by [negotiate](super::negotiate), scrapers which do not ask for
+/// `escaping=allow-utf-8` get underscore-escaped names instead. The `OpenMetricsExporter` and the
+/// `OpenMetricsReader` always escape names, since their text is served as OpenMetrics 1.0 to every scraper; only the
+/// choice of suffixes applies to them.
+///
+/// Attribute keys starting with `__`, which Prometheus reserves for its own labels, are prefixed with `key` in every
+/// strategy.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TranslationStrategy {
+    /// Illegal characters are replaced by underscores and unit and `_total` suffixes are appended.
+    #[default]
+    UnderscoreEscapingWithSuffixes,
+    /// Names are kept as UTF-8 and unit and `_total` suffixes are appended.
+    NoUtf8EscapingWithSuffixes,
+    /// Names are kept as UTF-8 without any suffixes.
+    NoTranslation,
+}
+
+/// How timestamps of samples are written, see [ConversionOptions::with_timestamp_mode].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TimestampMode {
+    /// Seconds since the epoch as float, as specified by OpenMetrics.
+    ///
+    /// The Prometheus text format only supports milliseconds, which are written instead.
+    #[default]
+    Seconds,
+    /// Seconds since the epoch, truncated to millisecond precision, e.g. `1792260055.158`.
+    ///
+    /// The Prometheus text format writes the same milliseconds as integer, e.g. `1792260055158`.
+    Milliseconds,
+    /// No timestamps, the scraper assigns the scrape time to the samples.
+    None,
+}
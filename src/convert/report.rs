@@ -0,0 +1,71 @@
+use super::ConversionError;
+
+/// A report of the metrics which were skipped or altered during a conversion.
+///
+/// See [WriteOpenMetrics::write_as_openmetrics_with_report](super::WriteOpenMetrics::write_as_openmetrics_with_report).
+#[derive(Debug, Default)]
+pub struct ConversionReport {
+    /// Metrics which were not written. Only metrics skipped in [lenient](super::ConversionOptions::with_lenient)
+    /// mode appear here, otherwise the conversion fails instead.
+    pub dropped: Vec<DroppedMetric>,
+    /// Metrics which were written under a different name than their OpenTelemetry name.
+    pub renamed: Vec<RenamedMetric>,
+    /// Metrics whose unit could not be converted into a metric name suffix and was dropped.
+    pub dropped_units: Vec<DroppedUnit>,
+    /// Attribute keys which were written as a different label name.
+    pub sanitized_labels: Vec<SanitizedLabel>,
+}
+
+impl ConversionReport {
+    /// Returns `true` if all metrics were written without any alteration.
+    pub fn is_empty(&self) -> bool {
+        self.dropped.is_empty()
+            && self.renamed.is_empty()
+            && self.dropped_units.is_empty()
+            && self.sanitized_labels.is_empty()
+    }
+}
+
+/// A metric which was not written.
+#[derive(Debug)]
+pub struct DroppedMetric {
+    /// the name of the instrumentation scope
+    pub scope: String,
+    /// the OpenTelemetry name of the metric
+    pub metric: String,
+    /// the reason why the metric was not written
+    pub reason: ConversionError,
+}
+
+/// A metric which was written under a different name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RenamedMetric {
+    /// the name of the instrumentation scope
+    pub scope: String,
+    /// the OpenTelemetry name of the metric
+    pub original: String,
+    /// the name of the written metric family
+    pub name: String,
+}
+
+/// A metric whose unit was dropped.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DroppedUnit {
+    /// the name of the instrumentation scope
+    pub scope: String,
+    /// the OpenTelemetry name of the metric
+    pub metric: String,
+    /// the OpenTelemetry unit of the metric
+    pub unit: String,
+}
+
+/// An attribute key which was written as a different label name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SanitizedLabel {
+    /// the name of the written metric family
+    pub metric: String,
+    /// the OpenTelemetry attribute key
+    pub original: String,
+    /// the written label name
+    pub label: String,
+}
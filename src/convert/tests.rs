@@ -4,7 +4,8 @@ use insta::assert_snapshot;
 use opentelemetry::KeyValue;
 use opentelemetry_sdk::metrics::data::ScopeMetrics;
 use testsupport::metric_data::{
-    make_f64_gauge_metric, make_f64_histogram_metric, make_u64_counter_metric,
+    make_f64_exponential_histogram_metric, make_f64_gauge_metric, make_f64_histogram_metric,
+    make_u64_counter_metric,
 };
 use testsupport::resource_metrics::make_test_metrics;
 use ufmt::uwrite;
@@ -30,6 +31,15 @@ fn test_write_sanitized_name() {
     assert_eq!(output, "_1_metric");
 }
 
+#[test]
+fn test_sanitize_label_name() {
+    assert_eq!(sanitize_label_name("valid_label"), "valid_label");
+    assert_eq!(sanitize_label_name("http.method"), "http_method");
+    assert_eq!(sanitize_label_name("a:b__c"), "a_b_c");
+    assert_eq!(sanitize_label_name("1.label"), "key_1_label");
+    assert_eq!(sanitize_label_name("__name__"), "key_name_");
+}
+
 #[test]
 fn test_write_escaped() {
     let mut output = String::new();
@@ -51,6 +61,15 @@ fn test_write_escaped() {
     assert_eq!(output, "Simple string");
 }
 
+#[test]
+fn test_write_escaped_help() {
+    let mut output = String::new();
+
+    // Quotes are not escaped in Prometheus help texts
+    write_escaped_help(&mut output, "Line 1\nWindows \"quoted\" \\ BS").unwrap();
+    assert_eq!(output, "Line 1\\nWindows \"quoted\" \\\\ BS");
+}
+
 #[test]
 fn test_hash_attrs() {
     let attrs = [
@@ -81,13 +100,14 @@ fn test_hash_attrs() {
 
 #[test]
 fn test_write_attrs() {
+    let options = ConversionOptions::default();
     let mut output = String::new();
     let attrs = [
         KeyValue::new("key1", "value1"),
         KeyValue::new("key2", "value2"),
     ];
 
-    write_attrs(&mut output, attrs.iter()).unwrap();
+    write_attrs(&mut output, &options, attrs.iter(), &[]).unwrap();
     assert_eq!(output, "key1=\"value1\",key2=\"value2\"");
 
     // Test with attributes containing characters that need escaping
@@ -97,27 +117,73 @@ fn test_write_attrs() {
         KeyValue::new("key2", "value\"with\"quotes"),
     ];
 
-    write_attrs(&mut output, attrs_with_escapes.iter()).unwrap();
+    write_attrs(&mut output, &options, attrs_with_escapes.iter(), &[]).unwrap();
     assert_eq!(
         output,
         "key1=\"value\\nwith\\nnewlines\",key2=\"value\\\"with\\\"quotes\""
     );
+
+    // Values of keys which collide after sanitization are joined in the order of the original keys
+    output.clear();
+    let attrs = [
+        KeyValue::new("a_b", "second"),
+        KeyValue::new("z", "last"),
+        KeyValue::new("a.b", "first"),
+        KeyValue::new("a_a", "other"),
+    ];
+    write_attrs(&mut output, &options, attrs.iter(), &[]).unwrap();
+    assert_eq!(output, "a_a=\"other\",a_b=\"first;second\",z=\"last\"");
+
+    // Attributes clashing with reserved labels are renamed, the converter's own labels are not
+    output.clear();
+    let attrs = [
+        KeyValue::new("le", "x"),
+        KeyValue::new("otel.scope.name", "attr"),
+    ];
+    let labels = [KeyValue::new("otel_scope_name", "scope")];
+    write_attrs(&mut output, &options, attrs.iter(), &labels).unwrap();
+    assert_eq!(
+        output,
+        "exported_le=\"x\",exported_otel_scope_name=\"attr\",otel_scope_name=\"scope\""
+    );
+
+    // The prefix is repeated until the name is unique, scope labels are only reserved if they are written
+    output.clear();
+    let attrs = [
+        KeyValue::new("le", "x"),
+        KeyValue::new("exported_le", "y"),
+        KeyValue::new("otel_scope_name", "attr"),
+    ];
+    write_attrs(&mut output, &options, attrs.iter(), &[]).unwrap();
+    assert_eq!(
+        output,
+        "exported_exported_le=\"x\",exported_le=\"y\",otel_scope_name=\"attr\""
+    );
+
+    // UTF-8 label names are quoted instead of escaped
+    output.clear();
+    let options =
+        options.with_translation_strategy(TranslationStrategy::NoUtf8EscapingWithSuffixes);
+    let attrs = [
+        KeyValue::new("http.method", "GET"),
+        KeyValue::new("key_1", "value1"),
+    ];
+    write_attrs(&mut output, &options, attrs.iter(), &[]).unwrap();
+    assert_eq!(output, "\"http.method\"=\"GET\",key_1=\"value1\"");
 }
 
 #[test]
 fn test_make_scope_name_attrs() {
     let scope_name = "test_scope";
-    let attr = make_scope_name_attrs(scope_name);
+    let attr = make_scope_name_attrs(true, scope_name);
 
-    if cfg!(feature = "otel_scope_info") {
-        assert!(attr.is_some());
-        if let Some(kv) = attr {
-            assert_eq!(kv.key.as_str(), "otel_scope_name");
-            assert_eq!(kv.value.as_str(), "test_scope");
-        }
-    } else {
-        assert!(attr.is_none());
+    assert!(attr.is_some());
+    if let Some(kv) = attr {
+        assert_eq!(kv.key.as_str(), "otel_scope_name");
+        assert_eq!(kv.value.as_str(), "test_scope");
     }
+
+    assert!(make_scope_name_attrs(false, scope_name).is_none());
 }
 
 #[test]
@@ -130,16 +196,22 @@ fn test_to_timestamp() {
     let mut output = String::new();
     uwrite!(output, "{}", timestamp).unwrap();
     assert_eq!(output, "1625097600");
+
+    // Times before the epoch do not panic
+    let time = UNIX_EPOCH - Duration::from_secs(1);
+    output.clear();
+    uwrite!(output, "{}", to_timestamp(time)).unwrap();
+    assert_eq!(output, "0");
 }
 
-#[cfg(feature = "otel_scope_info")]
 #[test]
 fn test_write_otel_scope_info() {
     let resource_metrics = make_test_metrics();
     let scopes: Vec<&ScopeMetrics> = resource_metrics.scope_metrics().collect();
 
     let mut output = String::new();
-    write_otel_scope_info(&mut output, &scopes).unwrap();
+    let options = ConversionOptions::default();
+    write_otel_scope_info(&mut output, &options, Format::OpenMetrics, &scopes).unwrap();
 
     assert!(output.contains("# TYPE otel_scope info"));
     assert!(output.contains("otel_scope_info{"));
@@ -153,7 +225,7 @@ fn test_get_type() {
 
     for scope in scopes {
         for metric in scope.metrics() {
-            let result = get_type(metric.data());
+            let result = get_type(metric.data(), false);
             assert!(result.is_ok());
 
             // Check that the type is one of the expected values
@@ -177,11 +249,12 @@ fn test_write_values() {
 
         for metric in scope.metrics() {
             let mut output = String::new();
+            let options = ConversionOptions::default();
             let mut ctx = Context {
                 scope_name,
                 name: metric.name().to_owned(),
                 attr_buffer: String::from("staledata"),
-                ..Context::with_output(&mut output)
+                ..Context::with_output(&mut output, &options)
             };
             let result = write_values(&mut ctx, metric.data());
 
@@ -206,12 +279,13 @@ fn test_write_gauge() {
         .to_string();
 
     let mut output = String::new();
+    let options = ConversionOptions::default();
 
     let mut ctx = Context {
         attr_buffer: String::from("staledata"),
         name: "mygauge".to_owned(),
         scope_name: "myscope",
-        ..Context::with_output(&mut output)
+        ..Context::with_output(&mut output, &options)
     };
 
     write_gauge(&mut ctx, &metric).unwrap();
@@ -228,18 +302,26 @@ fn test_write_counter() {
         .unwrap()
         .as_secs_f64()
         .to_string();
+    let start_ts = metric
+        .start_time()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs_f64()
+        .to_string();
 
     let mut output = String::new();
+    let options = ConversionOptions::default();
 
     let mut ctx = Context {
         attr_buffer: String::from("staledata"),
         name: "mycounter".to_owned(),
         scope_name: "myscope",
-        ..Context::with_output(&mut output)
+        ..Context::with_output(&mut output, &options)
     };
     write_counter(&mut ctx, &metric).unwrap();
 
     let output = output.replace(&ts, "<TIMESTAMP>");
+    let output = output.replace(&start_ts, "<START_TIMESTAMP>");
     assert_snapshot!(output);
 }
 
@@ -266,12 +348,13 @@ fn test_write_histogram() {
         .to_string();
 
     let mut output = String::new();
+    let options = ConversionOptions::default().with_histogram_min_max(false);
 
     let mut ctx = Context {
         attr_buffer: String::from("staledata"),
         name: "myhistogram".to_owned(),
         scope_name: "myscope",
-        ..Context::with_output(&mut output)
+        ..Context::with_output(&mut output, &options)
     };
     write_histogram(&mut ctx, &metric).unwrap();
     let output = output.replace(&ts, "<TIMESTAMP>");
@@ -279,3 +362,486 @@ fn test_write_histogram() {
 
     assert_snapshot!(output);
 }
+
+#[test]
+fn test_write_exponential_histogram() {
+    let metric = make_f64_exponential_histogram_metric(vec![
+        (3.0, vec![KeyValue::new("kk", "v1")]),
+        (5.0, vec![KeyValue::new("kk", "v1")]),
+        (0.0, vec![KeyValue::new("kk", "v1")]),
+        (-1.5, vec![KeyValue::new("kk", "v1")]),
+        (12.0, vec![KeyValue::new("kk", "v2")]),
+    ]);
+    let ts = metric
+        .time()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs_f64()
+        .to_string();
+    let start_ts = metric
+        .start_time()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs_f64()
+        .to_string();
+
+    let mut output = String::new();
+    let options = ConversionOptions::default().with_histogram_min_max(false);
+
+    let mut ctx = Context {
+        attr_buffer: String::from("staledata"),
+        name: "myhistogram".to_owned(),
+        scope_name: "myscope",
+        ..Context::with_output(&mut output, &options)
+    };
+    write_exponential_histogram(&mut ctx, &metric).unwrap();
+    let output = output.replace(&ts, "<TIMESTAMP>");
+    let output = output.replace(&start_ts, "<START_TIMESTAMP>");
+
+    assert_snapshot!(output);
+}
+
+#[test]
+fn test_exponential_buckets_downscale() {
+    let metric = make_f64_exponential_histogram_metric(vec![
+        (1.5, vec![]),
+        (3.0, vec![]),
+        (5.0, vec![]),
+        (12.0, vec![]),
+        (100.0, vec![]),
+    ]);
+    let point = metric.data_points().next().unwrap();
+    let all: Vec<_> = ExponentialBuckets::new(
+        point.scale(),
+        point.negative_bucket(),
+        point.positive_bucket(),
+        None,
+    )
+    .iter(point.zero_threshold(), point.zero_count())
+    .collect();
+    let capped: Vec<_> = ExponentialBuckets::new(
+        point.scale(),
+        point.negative_bucket(),
+        point.positive_bucket(),
+        Some(4),
+    )
+    .iter(point.zero_threshold(), point.zero_count())
+    .collect();
+
+    assert!(all.len() > 4);
+    assert!(capped.len() <= 4);
+    assert_eq!(
+        all.iter().map(|b| b.1).sum::<u64>(),
+        capped.iter().map(|b| b.1).sum::<u64>()
+    );
+    assert!(capped.windows(2).all(|w| w[0].0 < w[1].0));
+    assert!(capped.last().unwrap().0 >= 100.0);
+
+    // Adding merges at the lower scale without losing counts
+    let mut sum = ExponentialBuckets::new(
+        point.scale(),
+        point.negative_bucket(),
+        point.positive_bucket(),
+        Some(4),
+    );
+    let scale = sum.scale;
+    sum.add(
+        &ExponentialBuckets::new(
+            point.scale(),
+            point.negative_bucket(),
+            point.positive_bucket(),
+            None,
+        ),
+        160,
+    );
+    assert_eq!(sum.scale, scale);
+    let doubled: Vec<_> = sum
+        .iter(point.zero_threshold(), point.zero_count())
+        .collect();
+    assert_eq!(
+        doubled,
+        capped.iter().map(|b| (b.0, 2 * b.1)).collect::<Vec<_>>()
+    );
+
+    // Limits which cannot be met at the lowest scale are raised
+    let options = ConversionOptions::default().with_max_exponential_buckets(1);
+    assert_eq!(options.max_exponential_buckets, Some(7));
+}
+
+#[test]
+fn test_exponential_buckets_overflow() {
+    let metric = make_f64_exponential_histogram_metric(vec![(1e-300, vec![]), (1e300, vec![])]);
+
+    let mut output = String::new();
+    let options = ConversionOptions::default()
+        .with_histogram_min_max(false)
+        .with_max_exponential_buckets(7);
+    let mut ctx = Context {
+        name: "myhistogram".to_owned(),
+        scope_name: "myscope",
+        ..Context::with_output(&mut output, &options)
+    };
+    write_exponential_histogram(&mut ctx, &metric).unwrap();
+
+    // The highest bucket's upper bound overflows at the lowest scale and is folded into `+Inf`
+    let bounds: Vec<_> = output
+        .lines()
+        .filter_map(|line| line.split("le=\"").nth(1))
+        .map(|rest| rest.split('"').next().unwrap())
+        .collect();
+    assert!(!bounds.contains(&"inf"), "{output}");
+    assert_eq!(bounds.last(), Some(&"+Inf"), "{output}");
+    assert_eq!(bounds.iter().filter(|b| **b == "+Inf").count(), 1);
+    assert!(output.contains("le=\"+Inf\"} 2 "), "{output}");
+}
+
+#[test]
+fn test_accumulated_totals_wrap() {
+    use testsupport::metric_data::make_u64_histogram_metric;
+
+    let counter = make_u64_counter_metric(vec![(u64::MAX, vec![])]);
+    let histogram = make_u64_histogram_metric(vec![(u64::MAX, vec![])]);
+    let mut accumulator = DeltaAccumulator::default();
+    for _ in 0..2 {
+        accumulator.select_metric("myscope", "mycounter");
+        accumulator.add_sum(counter.data_points().next().unwrap(), UNIX_EPOCH);
+        accumulator.select_metric("myscope", "myhistogram");
+        accumulator.add_histogram(histogram.data_points().next().unwrap(), UNIX_EPOCH);
+    }
+
+    accumulator.select_metric("myscope", "mycounter");
+    let series = accumulator.series::<u64>();
+    assert!(matches!(series[0].1.values, SeriesValues::Sum(total) if total == u64::MAX - 1));
+    accumulator.select_metric("myscope", "myhistogram");
+    let series = accumulator.series::<u64>();
+    let SeriesValues::Histogram(totals) = &series[0].1.values else {
+        panic!("expected histogram totals");
+    };
+    assert_eq!(totals.sum, u64::MAX - 1);
+    assert_eq!(totals.count, 2);
+}
+
+#[test]
+fn test_write_with_options() {
+    let metrics = make_test_metrics();
+    let options = ConversionOptions::default()
+        .with_scope_info(false)
+        .with_target_info(false)
+        .with_unit_suffixes(false)
+        .with_total_suffix(false)
+        .with_histogram_min_max(true)
+        .with_timestamps(false);
+
+    let mut output = String::new();
+    metrics
+        .write_as_openmetrics_with(&options, &mut output)
+        .unwrap();
+    let created: Vec<String> = output
+        .lines()
+        .filter(|line| line.split('{').next().unwrap().ends_with("_created"))
+        .map(|line| line.split(' ').nth(1).unwrap().to_owned())
+        .collect();
+    assert_eq!(created.len(), 2);
+    for created in created {
+        output = output.replace(&created, "<START_TIMESTAMP>");
+    }
+
+    assert_snapshot!(output);
+}
+
+#[test]
+fn test_write_prometheus() {
+    let metrics = make_test_metrics();
+    let options = ConversionOptions::default()
+        .with_scope_info(true)
+        .with_target_info(true)
+        .with_timestamps(false)
+        .with_histogram_min_max(false);
+
+    let mut output = String::new();
+    metrics
+        .write_as_prometheus_with(&options, &mut output)
+        .unwrap();
+
+    assert!(!output.contains("# EOF"));
+    assert!(!output.contains("# UNIT"));
+    assert!(!output.contains("_created"));
+    assert_snapshot!(output);
+}
+
+#[test]
+fn test_prometheus_timestamp() {
+    use std::time::Duration;
+
+    let time = UNIX_EPOCH + Duration::from_millis(1625097600123);
+    let options = ConversionOptions::default();
+    let mut output = String::new();
+    uwrite!(
+        output,
+        "{}",
+        sample_timestamp(&options, Format::Prometheus, time)
+    )
+    .unwrap();
+    assert_eq!(output, " 1625097600123");
+
+    output.clear();
+    let options = options.with_timestamps(false);
+    uwrite!(
+        output,
+        "{}",
+        sample_timestamp(&options, Format::Prometheus, time)
+    )
+    .unwrap();
+    assert_eq!(output, "");
+}
+
+#[test]
+fn test_timestamp_mode() {
+    use std::time::Duration;
+
+    let time = UNIX_EPOCH + Duration::from_millis(1625097600123);
+    for (mode, expected) in [
+        (TimestampMode::Seconds, " 1625097600.123"),
+        (TimestampMode::Milliseconds, " 1625097600.123"),
+        (TimestampMode::None, ""),
+    ] {
+        let options = ConversionOptions::default().with_timestamp_mode(mode);
+        let mut output = String::new();
+        uwrite!(
+            output,
+            "{}",
+            sample_timestamp(&options, Format::OpenMetrics, time)
+        )
+        .unwrap();
+        assert_eq!(output, expected, "{mode:?}");
+    }
+
+    // Milliseconds are truncated and zero-padded in OpenMetrics, and written as integer in Prometheus
+    let time = UNIX_EPOCH + Duration::from_micros(1_625_097_600_005_900);
+    let options = ConversionOptions::default().with_timestamp_mode(TimestampMode::Milliseconds);
+    let mut output = String::new();
+    uwrite!(
+        output,
+        "{}|{}",
+        sample_timestamp(&options, Format::OpenMetrics, time),
+        sample_timestamp(&options, Format::Prometheus, time)
+    )
+    .unwrap();
+    assert_eq!(output, " 1625097600.005| 1625097600005");
+}
+
+#[test]
+fn test_negotiate() {
+    // Prometheus 3 scrape header
+    let format = negotiate(
+        "application/openmetrics-text;version=1.0.0;escaping=underscores;q=0.5,\
+         application/openmetrics-text;version=0.0.1;q=0.4,\
+         text/plain;version=0.0.4;q=0.3,*/*;q=0.2",
+    );
+    assert!(format.is_openmetrics());
+    assert_eq!(
+        format.content_type(),
+        "application/openmetrics-text; version=1.0.0; charset=utf-8; escaping=underscores"
+    );
+
+    let format = negotiate("text/plain;version=0.0.4;q=0.9,application/openmetrics-text;q=0.5");
+    assert_eq!(format, ExpositionFormat::PROMETHEUS);
+    assert_eq!(format.content_type(), PROMETHEUS_MIME_TYPE);
+
+    assert_eq!(
+        negotiate("application/openmetrics-text"),
+        ExpositionFormat::OPENMETRICS
+    );
+    assert_eq!(negotiate("*/*"), ExpositionFormat::PROMETHEUS);
+    assert_eq!(negotiate(""), ExpositionFormat::PROMETHEUS);
+    assert_eq!(
+        negotiate("application/json, application/openmetrics-text;version=2.0.0"),
+        ExpositionFormat::PROMETHEUS
+    );
+    assert_eq!(
+        negotiate("application/openmetrics-text;q=0, text/html"),
+        ExpositionFormat::PROMETHEUS
+    );
+
+    let mut output = String::new();
+    ExpositionFormat::OPENMETRICS
+        .write_as(
+            &make_test_metrics(),
+            &ConversionOptions::default(),
+            &mut output,
+        )
+        .unwrap();
+    assert!(output.ends_with("# EOF\n"));
+}
+
+#[test]
+fn test_write_as_openmetrics_io() {
+    let metrics = make_test_metrics();
+    let mut output = Vec::new();
+    metrics.write_as_openmetrics_io(&mut output).unwrap();
+    assert_eq!(
+        String::from_utf8(output).unwrap(),
+        metrics.to_openmetrics_string().unwrap()
+    );
+
+    struct Failing;
+
+    impl io::Write for Failing {
+        fn write(&mut self, _buf: &[u8]) -> io::Result<usize> {
+            Err(io::ErrorKind::BrokenPipe.into())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    let err = metrics.write_as_openmetrics_io(&mut Failing).unwrap_err();
+    assert!(matches!(&err, ConversionError::Io(err) if err.kind() == io::ErrorKind::BrokenPipe));
+    assert_eq!(io::Error::from(err).kind(), io::ErrorKind::BrokenPipe);
+}
+
+#[test]
+fn test_encoder_chunks() {
+    let metrics = make_test_metrics();
+    let options = ConversionOptions::default();
+
+    let mut encoder = Encoder::new(&metrics, &options, 1);
+    let mut chunks = Vec::new();
+    while let Some(chunk) = encoder.next_chunk().unwrap() {
+        chunks.push(chunk.to_owned());
+    }
+    assert_eq!(encoder.next_chunk().unwrap(), None);
+    assert!(chunks.len() > 3);
+    // every metric is in a chunk of its own
+    assert!(chunks.iter().skip(1).all(|chunk| chunk.starts_with("# ")));
+    assert_eq!(chunks.concat(), metrics.to_openmetrics_string().unwrap());
+
+    // A chunk size of zero still writes one piece per chunk
+    let mut encoder = Encoder::new(&metrics, &options, 0);
+    let mut zero_chunks = Vec::new();
+    while let Some(chunk) = encoder.next_chunk().unwrap() {
+        zero_chunks.push(chunk.to_owned());
+    }
+    assert_eq!(zero_chunks, chunks);
+
+    let mut encoder = ExpositionFormat::PROMETHEUS.encoder(&metrics, &options, usize::MAX);
+    assert_eq!(
+        encoder.next_chunk().unwrap(),
+        Some(metrics.to_prometheus_string().unwrap().as_str())
+    );
+    assert_eq!(encoder.next_chunk().unwrap(), None);
+}
+
+#[test]
+fn test_write_histogram_values_with_exemplars() {
+    use std::time::Duration;
+
+    let attribute = KeyValue::new("user", "me");
+    let exemplar = |value, secs| exemplar::ExemplarData {
+        value,
+        time: UNIX_EPOCH + Duration::from_secs(secs),
+        trace_id: [0xab; 16],
+        span_id: [0x01; 8],
+        attributes: vec![&attribute],
+    };
+    let values = HistogramValues {
+        count: 4,
+        sum: Some(108.0),
+        min: None,
+        max: None,
+        buckets: [(1.0, 1), (10.0, 2)].into_iter(),
+        exemplars: vec![exemplar(5.0, 1), exemplar(7.0, 2), exemplar(100.0, 3)],
+        created: Some(UNIX_EPOCH),
+    };
+
+    let mut output = String::new();
+    let mut attrs = String::from("kk=\"v1\"");
+    let options = ConversionOptions::default().with_timestamps(false);
+    let name = MetricName {
+        name: "myhistogram",
+        quoted: false,
+    };
+    write_histogram_values(&mut output, &options, name, &mut attrs, "", values).unwrap();
+    assert_snapshot!(output);
+}
+
+#[test]
+fn test_write_exemplar_timestamp() {
+    use std::time::Duration;
+
+    let exemplar = exemplar::ExemplarData {
+        value: 2.0,
+        time: UNIX_EPOCH + Duration::from_millis(1500),
+        trace_id: [0; 16],
+        span_id: [0; 8],
+        attributes: vec![],
+    };
+    for mode in [
+        TimestampMode::Seconds,
+        TimestampMode::Milliseconds,
+        TimestampMode::None,
+    ] {
+        let mut output = String::new();
+        let options = ConversionOptions::default().with_timestamp_mode(mode);
+        exemplar::write_exemplar(&mut output, &options, &exemplar).unwrap();
+        assert_eq!(output, " # {} 2 1.5");
+    }
+}
+
+#[test]
+fn test_write_exemplar_label_limit() {
+    use std::time::Duration;
+
+    let long = KeyValue::new("long", "x".repeat(62));
+    let short = KeyValue::new("a", "b");
+    let exemplar = exemplar::ExemplarData {
+        value: 1.5,
+        time: UNIX_EPOCH + Duration::from_millis(1500),
+        trace_id: [0; 16],
+        span_id: [0; 8],
+        attributes: vec![&long, &short],
+    };
+    let mut output = String::new();
+    exemplar::write_exemplar(&mut output, &ConversionOptions::default(), &exemplar).unwrap();
+    assert_eq!(
+        output,
+        format!(" # {{a=\"b\",long=\"{}\"}} 1.5 1.5", "x".repeat(62))
+    );
+
+    // trace and span ids take 63 of the 128 characters
+    let exemplar = exemplar::ExemplarData {
+        trace_id: [0x12; 16],
+        span_id: [0x34; 8],
+        ..exemplar
+    };
+    output.clear();
+    exemplar::write_exemplar(&mut output, &ConversionOptions::default(), &exemplar).unwrap();
+    assert_eq!(
+        output,
+        format!(
+            " # {{trace_id=\"{}\",span_id=\"{}\",a=\"b\"}} 1.5 1.5",
+            "12".repeat(16),
+            "34".repeat(8)
+        )
+    );
+
+    // attributes named like the id labels are dropped, colliding keys are joined
+    let trace = KeyValue::new("trace_id", "attr");
+    let dotted = KeyValue::new("a.b", "first");
+    let underscored = KeyValue::new("a_b", "second");
+    let exemplar = exemplar::ExemplarData {
+        span_id: [0; 8],
+        attributes: vec![&underscored, &trace, &dotted],
+        ..exemplar
+    };
+    output.clear();
+    exemplar::write_exemplar(&mut output, &ConversionOptions::default(), &exemplar).unwrap();
+    assert_eq!(
+        output,
+        format!(
+            " # {{trace_id=\"{}\",a_b=\"first;second\"}} 1.5 1.5",
+            "12".repeat(16)
+        )
+    );
+}
@@ -0,0 +1,100 @@
+#[cfg(feature = "gzip")]
+use std::io::Write;
+
+use bytes::Bytes;
+
+/// The content encodings which the HTTP integrations can respond with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ContentEncoding {
+    Identity,
+    #[cfg(feature = "gzip")]
+    Gzip,
+    #[cfg(feature = "zstd")]
+    Zstd,
+}
+
+impl ContentEncoding {
+    /// The supported compressions, in order of preference.
+    const COMPRESSIONS: &[ContentEncoding] = &[
+        #[cfg(feature = "zstd")]
+        ContentEncoding::Zstd,
+        #[cfg(feature = "gzip")]
+        ContentEncoding::Gzip,
+    ];
+
+    /// Selects the encoding of the response from the values of the `Accept-Encoding` headers of a request.
+    ///
+    /// Among the accepted encodings with the highest quality, those for which `cached` returns `true` are preferred.
+    pub(crate) fn negotiate<'a>(
+        accept_encoding: impl IntoIterator<Item = &'a str>,
+        cached: impl Fn(Self) -> bool,
+    ) -> Self {
+        let codings: Vec<(&str, f32)> = accept_encoding
+            .into_iter()
+            .flat_map(|value| value.split(','))
+            .map(|coding| {
+                let mut parts = coding.split(';');
+                let name = parts.next().unwrap_or_default().trim();
+                let quality = parts
+                    .filter_map(|param| param.trim().strip_prefix("q="))
+                    .find_map(|q| q.trim().parse::<f32>().ok())
+                    .unwrap_or(1.0);
+                (name, quality)
+            })
+            .collect();
+        let quality = |encoding: ContentEncoding| {
+            let name = encoding.header_value().unwrap_or("identity");
+            codings
+                .iter()
+                .find(|(coding, _)| coding.eq_ignore_ascii_case(name))
+                .or_else(|| codings.iter().find(|(coding, _)| *coding == "*"))
+                .map_or(0.0, |(_, quality)| *quality)
+        };
+
+        let mut best = ContentEncoding::Identity;
+        let mut best_rank = (0.0, false);
+        for &encoding in Self::COMPRESSIONS {
+            let rank = (quality(encoding), cached(encoding));
+            if rank.0 > 0.0 && rank > best_rank {
+                best = encoding;
+                best_rank = rank;
+            }
+        }
+        best
+    }
+
+    /// Returns the value of the `Content-Encoding` header, if any.
+    pub(crate) fn header_value(self) -> Option<&'static str> {
+        match self {
+            ContentEncoding::Identity => None,
+            #[cfg(feature = "gzip")]
+            ContentEncoding::Gzip => Some("gzip"),
+            #[cfg(feature = "zstd")]
+            ContentEncoding::Zstd => Some("zstd"),
+        }
+    }
+
+    /// Encodes `text` for the response body.
+    pub(crate) fn encode(self, text: &[u8]) -> Bytes {
+        match self {
+            ContentEncoding::Identity => Bytes::copy_from_slice(text),
+            #[cfg(feature = "gzip")]
+            ContentEncoding::Gzip => {
+                let mut encoder =
+                    flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::fast());
+                encoder
+                    .write_all(text)
+                    .and_then(|()| encoder.finish())
+                    .map(Bytes::from)
+                    .expect("compressing into a Vec should not fail")
+            }
+            #[cfg(feature = "zstd")]
+            ContentEncoding::Zstd => zstd::bulk::compress(text, zstd::DEFAULT_COMPRESSION_LEVEL)
+                .map(Bytes::from)
+                .expect("compressing into a Vec should not fail"),
+        }
+    }
+}
+
+/// The methods allowed on the metrics endpoints.
+pub(crate) const ALLOWED_METHODS: &str = "GET, HEAD";
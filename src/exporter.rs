@@ -1,27 +1,62 @@
-use std::ops::DerefMut;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex, PoisonError, RwLock};
 use std::time::Duration;
 
 use opentelemetry_sdk::error::{OTelSdkError, OTelSdkResult};
 use opentelemetry_sdk::metrics::Temporality;
 use opentelemetry_sdk::metrics::data::ResourceMetrics;
 use opentelemetry_sdk::metrics::exporter::PushMetricExporter;
-use tokio::sync::{Mutex, RwLock};
 
-use crate::convert::WriteOpenMetrics;
+use crate::convert::{ConversionOptions, DeltaAccumulator};
+#[cfg(any(feature = "gzip", feature = "zstd"))]
+use crate::encoding::ContentEncoding;
+#[cfg(feature = "server")]
+use crate::server::{MetricsServerBuilder, ServerHandle};
 
 /// A [PushMetricsExporter] which writes metrics into an internal buffer in OpenMetrics text format.
+///
+/// Metrics with delta temporality are accumulated into cumulative totals, see [DeltaAccumulator].
+/// Metrics which cannot be converted are skipped, see [ConversionOptions::with_lenient].
+///
+/// The exporter does not depend on an async runtime: exports only hold a lock for swapping in the new text.
 #[derive(Debug, Clone)]
 pub struct OpenMetricsExporter {
-    buffer: Arc<RwLock<String>>,
+    buffer: Arc<RwLock<Exposition>>,
     backbuffer: Arc<Mutex<String>>,
+    accumulator: Arc<Mutex<DeltaAccumulator>>,
+    temporality: Temporality,
+    options: ConversionOptions,
+    #[cfg(feature = "gzip")]
+    gzip: bool,
+    #[cfg(feature = "zstd")]
+    zstd: bool,
+    #[cfg(feature = "server")]
+    servers: Arc<Mutex<Vec<Arc<ServerHandle>>>>,
+}
+
+/// The result of an export, swapped in as a whole.
+#[derive(Debug, Clone, Default)]
+struct Exposition {
+    text: Arc<str>,
+    #[cfg(feature = "gzip")]
+    gzip: Option<bytes::Bytes>,
+    #[cfg(feature = "zstd")]
+    zstd: Option<bytes::Bytes>,
 }
 
 impl Default for OpenMetricsExporter {
     fn default() -> Self {
         OpenMetricsExporter {
-            buffer: Arc::new(RwLock::new(String::new())),
+            buffer: Arc::default(),
             backbuffer: Arc::new(Mutex::new(String::new())),
+            accumulator: Arc::new(Mutex::new(DeltaAccumulator::default())),
+            temporality: Temporality::Cumulative,
+            options: ConversionOptions::default().with_lenient(true),
+            #[cfg(feature = "gzip")]
+            gzip: false,
+            #[cfg(feature = "zstd")]
+            zstd: false,
+            #[cfg(feature = "server")]
+            servers: Arc::default(),
         }
     }
 }
@@ -32,9 +67,144 @@ impl OpenMetricsExporter {
         Default::default()
     }
 
+    /// Sets the temporality which this exporter requests from the SDK. Defaults to [Temporality::Cumulative].
+    pub fn with_temporality(mut self, temporality: Temporality) -> Self {
+        self.temporality = temporality;
+        self
+    }
+
+    /// Sets the options of the conversion to OpenMetrics text.
+    ///
+    /// The lenient mode is always enabled, so a single unconvertible metric does not fail the whole export.
+    /// Names are always escaped with underscores, since the text is served as OpenMetrics 1.0 to every scraper, see
+    /// [TranslationStrategy](crate::convert::TranslationStrategy).
+    pub fn with_options(mut self, options: ConversionOptions) -> Self {
+        self.options = options.with_lenient(true);
+        self.options.legacy_names = true;
+        self
+    }
+
     /// Get a clone of the last-exported OpenMetrics text.
+    ///
+    /// This is the same as [sync_text](Self::sync_text) and never waits, it is only kept `async` for compatibility.
     pub async fn text(&self) -> String {
-        self.buffer.read().await.as_str().to_owned()
+        self.sync_text()
+    }
+
+    /// Get a clone of the last-exported OpenMetrics text, without the need for an async runtime.
+    pub fn sync_text(&self) -> String {
+        self.snapshot().as_ref().to_owned()
+    }
+
+    /// Get the last-exported OpenMetrics text without copying it.
+    ///
+    /// The snapshot is immutable and stays valid after later exports.
+    pub fn snapshot(&self) -> Arc<str> {
+        self.exposition().text
+    }
+
+    /// Get the result of the last export.
+    fn exposition(&self) -> Exposition {
+        self.buffer
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+}
+
+#[cfg(feature = "server")]
+impl OpenMetricsExporter {
+    /// Create a builder for an HTTP server which serves the last-exported text.
+    /// The server is stopped when this exporter is shut down.
+    pub fn server(&self) -> MetricsServerBuilder {
+        MetricsServerBuilder::new(self.clone())
+    }
+
+    pub(crate) fn register_server(&self, handle: Arc<ServerHandle>) {
+        self.servers
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(handle);
+    }
+}
+
+#[cfg(feature = "bytes")]
+impl OpenMetricsExporter {
+    /// Get the last-exported OpenMetrics text as [Bytes](bytes::Bytes) without copying it, e.g. for an HTTP body.
+    pub fn snapshot_bytes(&self) -> bytes::Bytes {
+        text_bytes(self.snapshot())
+    }
+}
+
+/// Wraps `text` into [Bytes](bytes::Bytes) without copying it.
+#[cfg(feature = "bytes")]
+fn text_bytes(text: Arc<str>) -> bytes::Bytes {
+    struct Snapshot(Arc<str>);
+
+    impl AsRef<[u8]> for Snapshot {
+        fn as_ref(&self) -> &[u8] {
+            self.0.as_bytes()
+        }
+    }
+
+    bytes::Bytes::from_owner(Snapshot(text))
+}
+
+#[cfg(feature = "gzip")]
+impl OpenMetricsExporter {
+    /// Enables caching a gzip-compressed copy of every export, which HTTP integrations serve to clients accepting it.
+    ///
+    /// Defaults to `false`, in which case the text is compressed per request.
+    pub fn with_gzip(mut self, enabled: bool) -> Self {
+        self.gzip = enabled;
+        self
+    }
+
+    /// Get the gzip-compressed copy of the last export, if enabled by [with_gzip](Self::with_gzip).
+    pub fn snapshot_gzip(&self) -> Option<bytes::Bytes> {
+        self.exposition().gzip
+    }
+}
+
+#[cfg(feature = "zstd")]
+impl OpenMetricsExporter {
+    /// Enables caching a zstd-compressed copy of every export, which HTTP integrations serve to clients accepting it.
+    ///
+    /// Defaults to `false`, in which case the text is compressed per request.
+    pub fn with_zstd(mut self, enabled: bool) -> Self {
+        self.zstd = enabled;
+        self
+    }
+
+    /// Get the zstd-compressed copy of the last export, if enabled by [with_zstd](Self::with_zstd).
+    pub fn snapshot_zstd(&self) -> Option<bytes::Bytes> {
+        self.exposition().zstd
+    }
+}
+
+#[cfg(any(feature = "tower", feature = "actix"))]
+impl OpenMetricsExporter {
+    /// Get the last export in the best encoding for the given `Accept-Encoding` header values.
+    /// Cached compressed copies are preferred, other compressions are applied on the fly.
+    pub(crate) fn encoded_snapshot<'a>(
+        &self,
+        accept_encoding: impl IntoIterator<Item = &'a str>,
+    ) -> (ContentEncoding, bytes::Bytes) {
+        let exposition = self.exposition();
+        let cached = |encoding| match encoding {
+            ContentEncoding::Identity => None,
+            #[cfg(feature = "gzip")]
+            ContentEncoding::Gzip => exposition.gzip.clone(),
+            #[cfg(feature = "zstd")]
+            ContentEncoding::Zstd => exposition.zstd.clone(),
+        };
+        let encoding =
+            ContentEncoding::negotiate(accept_encoding, |encoding| cached(encoding).is_some());
+        let body = match encoding {
+            ContentEncoding::Identity => text_bytes(exposition.text),
+            _ => cached(encoding).unwrap_or_else(|| encoding.encode(exposition.text.as_bytes())),
+        };
+        (encoding, body)
     }
 }
 
@@ -42,16 +212,31 @@ impl PushMetricExporter for OpenMetricsExporter {
     async fn export(&self, metrics: &ResourceMetrics) -> OTelSdkResult {
         #[cfg(feature = "tracing")]
         tracing::debug!("Exporting metrics");
-        let mut backbuffer = self.backbuffer.lock().await;
+        let mut backbuffer = self
+            .backbuffer
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
         backbuffer.clear();
-        metrics
-            .write_as_openmetrics(backbuffer.deref_mut())
+        self.accumulator
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .write_as_openmetrics_with(metrics, &self.options, &mut *backbuffer)
             .map_err(|err| {
                 OTelSdkError::InternalFailure(format!("Failed to write to buffer: {err}"))
             })?;
 
-        let mut frontbuffer = self.buffer.write().await;
-        std::mem::swap(frontbuffer.deref_mut(), backbuffer.deref_mut());
+        let exposition = Exposition {
+            text: Arc::from(backbuffer.as_str()),
+            #[cfg(feature = "gzip")]
+            gzip: self
+                .gzip
+                .then(|| ContentEncoding::Gzip.encode(backbuffer.as_bytes())),
+            #[cfg(feature = "zstd")]
+            zstd: self
+                .zstd
+                .then(|| ContentEncoding::Zstd.encode(backbuffer.as_bytes())),
+        };
+        *self.buffer.write().unwrap_or_else(PoisonError::into_inner) = exposition;
 
         Ok(())
     }
@@ -60,11 +245,26 @@ impl PushMetricExporter for OpenMetricsExporter {
         Ok(())
     }
 
+    #[cfg(not(feature = "server"))]
     fn shutdown_with_timeout(&self, _timeout: Duration) -> OTelSdkResult {
         Ok(())
     }
 
+    #[cfg(feature = "server")]
+    fn shutdown_with_timeout(&self, timeout: Duration) -> OTelSdkResult {
+        let servers =
+            std::mem::take(&mut *self.servers.lock().unwrap_or_else(PoisonError::into_inner));
+        let deadline = std::time::Instant::now() + timeout;
+        let mut result = Ok(());
+        for server in servers {
+            if !server.shutdown(deadline) {
+                result = Err(OTelSdkError::Timeout(timeout));
+            }
+        }
+        result
+    }
+
     fn temporality(&self) -> Temporality {
-        Temporality::Cumulative
+        self.temporality
     }
 }
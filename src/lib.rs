@@ -1,5 +1,17 @@
+#[cfg(feature = "actix")]
+pub mod actix;
 pub mod convert;
 #[cfg(feature = "exporter")]
 pub mod exporter;
+#[cfg(feature = "reader")]
+pub mod reader;
+#[cfg(feature = "server")]
+pub mod server;
+#[cfg(feature = "tower")]
+pub mod tower;
 
+#[cfg(any(feature = "gzip", feature = "zstd"))]
+// content negotiation is only used by the HTTP integrations
+#[cfg_attr(not(any(feature = "tower", feature = "actix")), allow(dead_code))]
+mod encoding;
 mod format;
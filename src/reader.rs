@@ -0,0 +1,104 @@
+use std::sync::{Arc, Mutex, PoisonError, Weak};
+use std::time::Duration;
+
+use opentelemetry_sdk::error::{OTelSdkError, OTelSdkResult};
+use opentelemetry_sdk::metrics::data::ResourceMetrics;
+use opentelemetry_sdk::metrics::reader::MetricReader;
+use opentelemetry_sdk::metrics::{InstrumentKind, ManualReader, Pipeline, Temporality};
+
+use crate::convert::{ConversionOptions, DeltaAccumulator};
+
+/// A [MetricReader] which collects metrics when they are requested through [text](Self::text).
+///
+/// Unlike the [OpenMetricsExporter](crate::exporter::OpenMetricsExporter), no [PeriodicReader] is needed and every
+/// scrape sees the current values.
+///
+/// Metrics with delta temporality are accumulated into cumulative totals, see [DeltaAccumulator].
+/// Metrics which cannot be converted are skipped, see [ConversionOptions::with_lenient].
+///
+/// [PeriodicReader]: opentelemetry_sdk::metrics::PeriodicReader
+#[derive(Debug, Clone)]
+pub struct OpenMetricsReader {
+    inner: Arc<ManualReader>,
+    accumulator: Arc<Mutex<DeltaAccumulator>>,
+    temporality: Temporality,
+    options: ConversionOptions,
+}
+
+impl Default for OpenMetricsReader {
+    fn default() -> Self {
+        OpenMetricsReader {
+            inner: Arc::new(ManualReader::default()),
+            accumulator: Arc::new(Mutex::new(DeltaAccumulator::default())),
+            temporality: Temporality::Cumulative,
+            options: ConversionOptions::default().with_lenient(true),
+        }
+    }
+}
+
+impl OpenMetricsReader {
+    /// Sets the temporality which this reader requests from the SDK. Defaults to [Temporality::Cumulative].
+    ///
+    /// Must be called before the reader is registered with a meter provider.
+    pub fn with_temporality(mut self, temporality: Temporality) -> Self {
+        self.inner = Arc::new(
+            ManualReader::builder()
+                .with_temporality(temporality)
+                .build(),
+        );
+        self.temporality = temporality;
+        self
+    }
+
+    /// Sets the options of the conversion to OpenMetrics text.
+    ///
+    /// The lenient mode is always enabled, so a single unconvertible metric does not fail the whole scrape.
+    /// Names are always escaped with underscores, since the text is meant to be served as OpenMetrics 1.0, see
+    /// [TranslationStrategy](crate::convert::TranslationStrategy).
+    pub fn with_options(mut self, options: ConversionOptions) -> Self {
+        self.options = options.with_lenient(true);
+        self.options.legacy_names = true;
+        self
+    }
+
+    /// Collect the current metrics and return them as OpenMetrics text.
+    pub fn text(&self) -> Result<String, OTelSdkError> {
+        let mut metrics = ResourceMetrics::default();
+        // Hold the accumulator while collecting, so concurrent scrapes add their deltas in order.
+        let mut accumulator = self
+            .accumulator
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        self.inner.collect(&mut metrics)?;
+
+        let mut text = String::new();
+        accumulator
+            .write_as_openmetrics_with(&metrics, &self.options, &mut text)
+            .map_err(|err| {
+                OTelSdkError::InternalFailure(format!("Failed to write to buffer: {err}"))
+            })?;
+        Ok(text)
+    }
+}
+
+impl MetricReader for OpenMetricsReader {
+    fn register_pipeline(&self, pipeline: Weak<Pipeline>) {
+        self.inner.register_pipeline(pipeline);
+    }
+
+    fn collect(&self, rm: &mut ResourceMetrics) -> OTelSdkResult {
+        self.inner.collect(rm)
+    }
+
+    fn force_flush(&self) -> OTelSdkResult {
+        self.inner.force_flush()
+    }
+
+    fn shutdown_with_timeout(&self, timeout: Duration) -> OTelSdkResult {
+        self.inner.shutdown_with_timeout(timeout)
+    }
+
+    fn temporality(&self, _kind: InstrumentKind) -> Temporality {
+        self.temporality
+    }
+}
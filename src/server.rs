@@ -0,0 +1,282 @@
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::convert::MIME_TYPE;
+use crate::exporter::OpenMetricsExporter;
+
+/// The default address of the metrics endpoint, the port registered for OpenTelemetry Prometheus exporters.
+pub const DEFAULT_ADDRESS: SocketAddr =
+    SocketAddr::new(std::net::IpAddr::V4(Ipv4Addr::UNSPECIFIED), 9464);
+
+/// The default path of the metrics endpoint.
+pub const DEFAULT_PATH: &str = "/metrics";
+
+/// How long a connection may stay silent while sending its request head. Kept short, as waiting connections hold a
+/// worker.
+const READ_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// How long a connection may take to receive the response.
+const WRITE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The maximum size of a request head. Larger requests are rejected.
+const MAX_REQUEST_HEAD: u64 = 8 * 1024;
+
+/// The number of threads serving connections. Further connections wait in the listen backlog.
+const WORKERS: usize = 4;
+
+/// How long to pause accepting after an error, e.g. when running out of file descriptors.
+const ACCEPT_BACKOFF: Duration = Duration::from_millis(100);
+
+/// Builder for a [MetricsServer], see [OpenMetricsExporter::server].
+#[derive(Debug, Clone)]
+pub struct MetricsServerBuilder {
+    exporter: OpenMetricsExporter,
+    address: SocketAddr,
+    path: String,
+}
+
+impl MetricsServerBuilder {
+    pub(crate) fn new(exporter: OpenMetricsExporter) -> Self {
+        MetricsServerBuilder {
+            exporter,
+            address: DEFAULT_ADDRESS,
+            path: DEFAULT_PATH.to_owned(),
+        }
+    }
+
+    /// Sets the address to listen on. Defaults to [DEFAULT_ADDRESS].
+    pub fn with_address(mut self, address: impl Into<SocketAddr>) -> Self {
+        self.address = address.into();
+        self
+    }
+
+    /// Sets the path to serve the metrics on. Defaults to [DEFAULT_PATH].
+    pub fn with_path(mut self, path: impl Into<String>) -> Self {
+        self.path = path.into();
+        self
+    }
+
+    /// Binds the listener and starts serving on background threads, which handle a few connections at a time.
+    ///
+    /// The server is stopped by [MetricsServer::shutdown] or when the exporter is shut down.
+    pub fn start(self) -> io::Result<MetricsServer> {
+        let listener = TcpListener::bind(self.address)?;
+        let local_addr = listener.local_addr()?;
+        let (done_tx, done_rx) = mpsc::channel();
+        let handle = Arc::new(ServerHandle {
+            local_addr,
+            stopped: AtomicBool::new(false),
+            done: Mutex::new(Some(done_rx)),
+        });
+
+        // Accepted connections are handed to a fixed pool of workers. The channel holds no connections, so accepting
+        // blocks while all workers are busy.
+        let (stream_tx, stream_rx) = mpsc::sync_channel::<TcpStream>(0);
+        let stream_rx = Arc::new(Mutex::new(stream_rx));
+        let path: Arc<str> = self.path.into();
+        for _ in 0..WORKERS {
+            let stream_rx = stream_rx.clone();
+            let exporter = self.exporter.clone();
+            let path = path.clone();
+            thread::Builder::new()
+                .name("openmetrics-connection".to_owned())
+                .spawn(move || {
+                    loop {
+                        let stream = stream_rx
+                            .lock()
+                            .unwrap_or_else(PoisonError::into_inner)
+                            .recv();
+                        let Ok(stream) = stream else {
+                            // the server stopped
+                            break;
+                        };
+                        handle_connection(stream, &exporter, &path);
+                    }
+                })?;
+        }
+
+        let server_handle = handle.clone();
+        thread::Builder::new()
+            .name("openmetrics-server".to_owned())
+            .spawn(move || {
+                for stream in listener.incoming() {
+                    if server_handle.stopped.load(Ordering::Acquire) {
+                        break;
+                    }
+                    match stream {
+                        Ok(stream) => {
+                            if stream_tx.send(stream).is_err() {
+                                break;
+                            }
+                        }
+                        Err(_) => thread::sleep(ACCEPT_BACKOFF),
+                    }
+                }
+                drop(stream_tx);
+                let _ = done_tx.send(());
+            })?;
+
+        self.exporter.register_server(handle.clone());
+        Ok(MetricsServer { handle })
+    }
+}
+
+/// A running HTTP server serving the text of an [OpenMetricsExporter].
+///
+/// Dropping the server does not stop it.
+#[derive(Debug)]
+pub struct MetricsServer {
+    handle: Arc<ServerHandle>,
+}
+
+impl MetricsServer {
+    /// Returns the address the server listens on.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.handle.local_addr
+    }
+
+    /// Stops accepting connections and waits up to `timeout` for the listener to close.
+    /// Returns [io::ErrorKind::TimedOut] if the server did not stop in time.
+    pub fn shutdown(self, timeout: Duration) -> io::Result<()> {
+        if self.handle.shutdown(Instant::now() + timeout) {
+            Ok(())
+        } else {
+            Err(io::ErrorKind::TimedOut.into())
+        }
+    }
+}
+
+/// The shared state to stop a [MetricsServer].
+#[derive(Debug)]
+pub(crate) struct ServerHandle {
+    local_addr: SocketAddr,
+    stopped: AtomicBool,
+    done: Mutex<Option<Receiver<()>>>,
+}
+
+impl ServerHandle {
+    /// Stops the server and waits until `deadline` for the listener to close. Returns `false` on timeout.
+    pub(crate) fn shutdown(&self, deadline: Instant) -> bool {
+        let Some(done) = self
+            .done
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .take()
+        else {
+            // already stopped
+            return true;
+        };
+        self.stopped.store(true, Ordering::Release);
+
+        // Wake up the blocking accept with a connection of our own
+        let mut wake_addr = self.local_addr;
+        if wake_addr.ip().is_unspecified() {
+            wake_addr.set_ip(match wake_addr {
+                SocketAddr::V4(_) => Ipv4Addr::LOCALHOST.into(),
+                SocketAddr::V6(_) => Ipv6Addr::LOCALHOST.into(),
+            });
+        }
+        let time_left = || deadline.saturating_duration_since(Instant::now());
+        let _ = TcpStream::connect_timeout(&wake_addr, time_left());
+
+        !matches!(
+            done.recv_timeout(time_left()),
+            Err(RecvTimeoutError::Timeout)
+        )
+    }
+}
+
+/// Serves a single request on `stream` and closes the connection.
+fn handle_connection(stream: TcpStream, exporter: &OpenMetricsExporter, path: &str) {
+    let _ = stream.set_read_timeout(Some(READ_TIMEOUT));
+    let _ = stream.set_write_timeout(Some(WRITE_TIMEOUT));
+    let Ok(read_half) = stream.try_clone() else {
+        return;
+    };
+    let mut reader = BufReader::new(read_half.take(MAX_REQUEST_HEAD));
+    let response = match read_request_line(&mut reader) {
+        Some((method, target)) => {
+            let target_path = target.split_once('?').map_or(target.as_str(), |(p, _)| p);
+            if target_path != path {
+                Response::status("404 Not Found")
+            } else if method == "GET" || method == "HEAD" {
+                Response {
+                    status: "200 OK",
+                    content_type: MIME_TYPE,
+                    body: Some(exporter.snapshot()),
+                    head: method == "HEAD",
+                }
+            } else {
+                Response::status("405 Method Not Allowed")
+            }
+        }
+        None => Response::status("400 Bad Request"),
+    };
+    let _ = response.write_to(&stream);
+}
+
+/// Reads the request head and returns the method and target of the request line.
+fn read_request_line(reader: &mut impl BufRead) -> Option<(String, String)> {
+    let mut line = String::new();
+    reader.read_line(&mut line).ok()?;
+    let mut parts = line.split_ascii_whitespace();
+    let method = parts.next()?.to_owned();
+    let target = parts.next()?.to_owned();
+    if !parts.next()?.starts_with("HTTP/1.") {
+        return None;
+    }
+
+    // Skip the headers, none of them change the response
+    loop {
+        line.clear();
+        match reader.read_line(&mut line) {
+            Ok(0) | Err(_) => return None,
+            Ok(_) if line == "\r\n" || line == "\n" => return Some((method, target)),
+            Ok(_) => {}
+        }
+    }
+}
+
+/// A minimal HTTP/1.1 response.
+struct Response {
+    status: &'static str,
+    content_type: &'static str,
+    body: Option<Arc<str>>,
+    head: bool,
+}
+
+impl Response {
+    /// A response without body.
+    fn status(status: &'static str) -> Self {
+        Response {
+            status,
+            content_type: "text/plain; charset=utf-8",
+            body: None,
+            head: false,
+        }
+    }
+
+    fn write_to(&self, mut stream: &TcpStream) -> io::Result<()> {
+        let body = self.body.as_deref().unwrap_or("");
+        write!(
+            stream,
+            "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n",
+            self.status,
+            self.content_type,
+            body.len()
+        )?;
+        if self.status.starts_with("405") {
+            stream.write_all(b"Allow: GET, HEAD\r\n")?;
+        }
+        stream.write_all(b"\r\n")?;
+        if !self.head {
+            stream.write_all(body.as_bytes())?;
+        }
+        stream.flush()
+    }
+}
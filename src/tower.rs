@@ -0,0 +1,94 @@
+use std::convert::Infallible;
+use std::future::{Ready, ready};
+use std::task::{Context, Poll};
+
+use bytes::Bytes;
+use http::header::{ACCEPT_ENCODING, ALLOW, CONTENT_ENCODING, CONTENT_TYPE, VARY};
+use http::{HeaderMap, HeaderValue, Method, Request, Response, StatusCode};
+use http_body_util::Full;
+use tower_service::Service;
+
+use crate::convert::MIME_TYPE;
+use crate::encoding::ALLOWED_METHODS;
+use crate::exporter::OpenMetricsExporter;
+
+/// A [Service] which responds to every request with the last-exported text of an [OpenMetricsExporter].
+///
+/// The text is compressed if the request accepts it, see [OpenMetricsExporter::with_gzip].
+/// Mount it with e.g. axum's `Router::route_service`.
+#[derive(Debug, Clone)]
+pub struct MetricsService {
+    exporter: OpenMetricsExporter,
+}
+
+impl MetricsService {
+    pub fn new(exporter: OpenMetricsExporter) -> Self {
+        MetricsService { exporter }
+    }
+}
+
+impl<B> Service<Request<B>> for MetricsService {
+    type Response = Response<Full<Bytes>>;
+    type Error = Infallible;
+    type Future = Ready<Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: Request<B>) -> Self::Future {
+        ready(Ok(metrics_response(
+            &self.exporter,
+            request.method(),
+            request.headers(),
+        )))
+    }
+}
+
+/// An axum handler which serves the last-exported text of the [OpenMetricsExporter] in the router state.
+///
+/// ```ignore
+/// let app = Router::new()
+///     .route("/metrics", get(handler))
+///     .with_state(exporter);
+/// ```
+#[cfg(feature = "axum")]
+pub async fn handler(
+    axum::extract::State(exporter): axum::extract::State<OpenMetricsExporter>,
+    method: Method,
+    headers: HeaderMap,
+) -> Response<Full<Bytes>> {
+    metrics_response(&exporter, &method, &headers)
+}
+
+/// Creates the response to a request for the metrics with `method` and `headers`.
+fn metrics_response(
+    exporter: &OpenMetricsExporter,
+    method: &Method,
+    headers: &HeaderMap,
+) -> Response<Full<Bytes>> {
+    if method != Method::GET && method != Method::HEAD {
+        let mut response = Response::new(Full::default());
+        *response.status_mut() = StatusCode::METHOD_NOT_ALLOWED;
+        response
+            .headers_mut()
+            .insert(ALLOW, HeaderValue::from_static(ALLOWED_METHODS));
+        return response;
+    }
+
+    let (encoding, body) = exporter.encoded_snapshot(
+        headers
+            .get_all(ACCEPT_ENCODING)
+            .iter()
+            .filter_map(|value| value.to_str().ok()),
+    );
+
+    let mut response = Response::new(Full::new(body));
+    let headers = response.headers_mut();
+    headers.insert(CONTENT_TYPE, HeaderValue::from_static(MIME_TYPE));
+    headers.insert(VARY, HeaderValue::from_static("accept-encoding"));
+    if let Some(content_encoding) = encoding.header_value() {
+        headers.insert(CONTENT_ENCODING, HeaderValue::from_static(content_encoding));
+    }
+    response
+}
@@ -0,0 +1,54 @@
+use std::io::Read;
+
+use actix_web::http::StatusCode;
+use actix_web::http::header::{ACCEPT_ENCODING, CONTENT_ENCODING, CONTENT_TYPE};
+use actix_web::{App, test};
+use opentelemetry::metrics::MeterProvider;
+use opentelemetry_openmetrics::actix::resource;
+use opentelemetry_openmetrics::convert::MIME_TYPE;
+use opentelemetry_openmetrics::exporter::OpenMetricsExporter;
+use opentelemetry_sdk::metrics::SdkMeterProvider;
+
+#[test]
+fn actix_serves_metrics() {
+    let exporter = OpenMetricsExporter::default();
+    let meter_provider = SdkMeterProvider::builder()
+        .with_periodic_exporter(exporter.clone())
+        .build();
+    let meter = meter_provider.meter("meter.one");
+    let gauge = meter.f64_gauge("a_gauge").build();
+    gauge.record(42.0, &[]);
+    meter_provider.force_flush().unwrap();
+
+    actix_web::rt::System::new().block_on(async {
+        let app =
+            test::init_service(App::new().service(resource("/metrics", exporter.clone()))).await;
+
+        let response =
+            test::call_service(&app, test::TestRequest::get().uri("/metrics").to_request()).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers().get(CONTENT_TYPE).unwrap(), MIME_TYPE);
+        assert!(response.headers().get(CONTENT_ENCODING).is_none());
+        assert_eq!(
+            test::read_body(response).await,
+            exporter.snapshot().as_bytes()
+        );
+
+        let request = test::TestRequest::get()
+            .uri("/metrics")
+            .insert_header((ACCEPT_ENCODING, "gzip"))
+            .to_request();
+        let response = test::call_service(&app, request).await;
+        assert_eq!(response.headers().get(CONTENT_ENCODING).unwrap(), "gzip");
+        let body = test::read_body(response).await;
+        let mut text = String::new();
+        flate2::read::GzDecoder::new(&body[..])
+            .read_to_string(&mut text)
+            .unwrap();
+        assert_eq!(text, *exporter.snapshot());
+
+        let response =
+            test::call_service(&app, test::TestRequest::post().uri("/metrics").to_request()).await;
+        assert_eq!(response.status(), StatusCode::METHOD_NOT_ALLOWED);
+    });
+}
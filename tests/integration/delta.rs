@@ -0,0 +1,206 @@
+use opentelemetry::KeyValue;
+use opentelemetry::metrics::MeterProvider;
+use opentelemetry_openmetrics::convert::{
+    ConversionError, ConversionOptions, DeltaAccumulator, WriteOpenMetrics,
+};
+use opentelemetry_sdk::metrics::data::ResourceMetrics;
+use opentelemetry_sdk::metrics::reader::MetricReader;
+use opentelemetry_sdk::metrics::{Aggregation, Instrument, SdkMeterProvider, Stream, Temporality};
+use testsupport::reader::TestMetricsReader;
+
+fn collect(reader: &TestMetricsReader) -> ResourceMetrics {
+    let mut metrics = ResourceMetrics::default();
+    reader.collect(&mut metrics).unwrap();
+    metrics
+}
+
+#[test]
+fn delta_metrics_are_accumulated() {
+    let reader = TestMetricsReader::with_temporality(Temporality::Delta);
+    let meter_provider = SdkMeterProvider::builder()
+        .with_reader(reader.clone())
+        .build();
+    let meter = meter_provider.meter("meter.delta");
+    let counter = meter.u64_counter("jobs").build();
+    let hist = meter.f64_histogram("latency").build();
+
+    let mut accumulator = DeltaAccumulator::default();
+    let created = |text: &str, series: &str| {
+        text.lines()
+            .find(|line| line.starts_with(series))
+            .map(|line| line.split(' ').nth(1).unwrap().to_owned())
+    };
+
+    counter.add(5, &[KeyValue::new("kk", "v1")]);
+    hist.record(3.0, &[]);
+    let mut first = String::new();
+    accumulator
+        .write_as_openmetrics(&collect(&reader), &mut first)
+        .unwrap();
+    assert!(first.contains("jobs_total{kk=\"v1\",otel_scope_name=\"meter.delta\"} 5 "));
+
+    counter.add(3, &[KeyValue::new("kk", "v1")]);
+    hist.record(30.0, &[]);
+    let mut second = String::new();
+    accumulator
+        .write_as_openmetrics(&collect(&reader), &mut second)
+        .unwrap();
+
+    assert!(second.contains("jobs_total{kk=\"v1\",otel_scope_name=\"meter.delta\"} 8 "));
+    assert!(second.contains("latency_count{otel_scope_name=\"meter.delta\"} 2 "));
+    assert!(second.contains("latency_sum{otel_scope_name=\"meter.delta\"} 33 "));
+    assert!(second.contains("latency_bucket{otel_scope_name=\"meter.delta\",le=\"5\"} 1 "));
+    assert!(second.contains("latency_bucket{otel_scope_name=\"meter.delta\",le=\"50\"} 2 "));
+    for series in [
+        "jobs_created{kk=\"v1\",otel_scope_name=\"meter.delta\"}",
+        "latency_created{otel_scope_name=\"meter.delta\"}",
+    ] {
+        assert!(created(&first, series).is_some());
+        assert_eq!(created(&first, series), created(&second, series));
+    }
+
+    counter.add(1, &[KeyValue::new("kk", "v1")]);
+    let mut third = String::new();
+    accumulator
+        .write_as_openmetrics_with(
+            &collect(&reader),
+            &ConversionOptions::default().with_created(false),
+            &mut third,
+        )
+        .unwrap();
+    assert!(third.contains("jobs_total{kk=\"v1\",otel_scope_name=\"meter.delta\"} 9 "));
+    assert!(!third.contains("_created"));
+}
+
+#[test]
+fn idle_delta_series_are_kept() {
+    let reader = TestMetricsReader::with_temporality(Temporality::Delta);
+    let meter_provider = SdkMeterProvider::builder()
+        .with_reader(reader.clone())
+        .build();
+    let meter = meter_provider.meter("meter.delta");
+    let counter = meter.u64_counter("jobs").build();
+    let hist = meter.f64_histogram("latency").build();
+
+    let mut accumulator = DeltaAccumulator::default();
+    let mut convert = || {
+        let mut text = String::new();
+        accumulator
+            .write_as_openmetrics(&collect(&reader), &mut text)
+            .unwrap();
+        text
+    };
+
+    counter.add(5, &[KeyValue::new("k", "a")]);
+    counter.add(2, &[KeyValue::new("k", "b")]);
+    hist.record(3.0, &[]);
+    let first = convert();
+
+    // No measurements at all: the SDK leaves out both metrics
+    let second = convert();
+    assert!(second.contains("# TYPE jobs counter\n"));
+    assert!(second.contains("jobs_total{k=\"a\",otel_scope_name=\"meter.delta\"} 5 "));
+    assert!(second.contains("jobs_total{k=\"b\",otel_scope_name=\"meter.delta\"} 2 "));
+    assert!(second.contains("# TYPE latency histogram\n"));
+    assert!(second.contains("latency_count{otel_scope_name=\"meter.delta\"} 1 "));
+
+    // Only one of the series has measurements
+    counter.add(1, &[KeyValue::new("k", "a")]);
+    let third = convert();
+    assert!(third.contains("jobs_total{k=\"a\",otel_scope_name=\"meter.delta\"} 6 "));
+    assert!(third.contains("jobs_total{k=\"b\",otel_scope_name=\"meter.delta\"} 2 "));
+    assert!(third.contains("latency_count{otel_scope_name=\"meter.delta\"} 1 "));
+
+    let created = |text: &str| {
+        text.lines()
+            .find(|line| line.starts_with("jobs_created{k=\"b\""))
+            .map(|line| line.split(' ').nth(1).unwrap().to_owned())
+    };
+    assert!(created(&first).is_some());
+    assert_eq!(created(&first), created(&second));
+    assert_eq!(created(&first), created(&third));
+}
+
+#[test]
+fn delta_exponential_histograms_are_accumulated() {
+    let reader = TestMetricsReader::with_temporality(Temporality::Delta);
+    let meter_provider = SdkMeterProvider::builder()
+        .with_reader(reader.clone())
+        .with_view(|_: &Instrument| {
+            Stream::builder()
+                .with_aggregation(Aggregation::Base2ExponentialHistogram {
+                    max_size: 160,
+                    max_scale: 2,
+                    record_min_max: true,
+                })
+                .build()
+                .ok()
+        })
+        .build();
+    let meter = meter_provider.meter("meter.delta");
+    let hist = meter.f64_histogram("latency").build();
+
+    let mut accumulator = DeltaAccumulator::default();
+    let options = ConversionOptions::default()
+        .with_scope_info(false)
+        .with_timestamps(false)
+        .with_histogram_min_max(false);
+    let mut convert = || {
+        let mut text = String::new();
+        accumulator
+            .write_as_openmetrics_with(&collect(&reader), &options, &mut text)
+            .unwrap();
+        text
+    };
+
+    hist.record(3.0, &[]);
+    let first = convert();
+    assert!(first.contains("latency_count{} 1\n"));
+    assert!(first.contains("latency_sum{} 3\n"));
+
+    hist.record(100.0, &[]);
+    hist.record(0.0, &[]);
+    let second = convert();
+    assert!(second.contains("latency_count{} 3\n"));
+    assert!(second.contains("latency_sum{} 103\n"));
+    assert!(second.contains("latency_bucket{le=\"0\"} 1\n"));
+    assert!(second.contains("latency_bucket{le=\"4\"} 2\n"));
+    assert!(second.contains("latency_bucket{le=\"+Inf\"} 3\n"));
+
+    // Negative observations leave out the sum from then on, also while idle
+    hist.record(-1.0, &[]);
+    let third = convert();
+    assert!(third.contains("latency_count{} 4\n"));
+    assert!(!third.contains("latency_sum"));
+    assert_eq!(convert(), third);
+}
+
+#[test]
+fn delta_metrics_are_rejected_without_accumulator() {
+    let reader = TestMetricsReader::with_temporality(Temporality::Delta);
+    let meter_provider = SdkMeterProvider::builder()
+        .with_reader(reader.clone())
+        .build();
+    let meter = meter_provider.meter("meter.delta");
+    meter.u64_counter("jobs").build().add(5, &[]);
+    meter.f64_gauge("level").build().record(1.5, &[]);
+    let metrics = collect(&reader);
+
+    let err = metrics.to_openmetrics_string().unwrap_err();
+    assert!(matches!(
+        err,
+        ConversionError::UnsupportedTemporality {
+            ref scope,
+            ref metric,
+            temporality: Temporality::Delta,
+        } if scope == "meter.delta" && metric == "jobs"
+    ));
+
+    let mut text = String::new();
+    metrics
+        .write_as_openmetrics_with(&ConversionOptions::default().with_lenient(true), &mut text)
+        .unwrap();
+    assert!(!text.contains("jobs"));
+    assert!(text.contains("level{"));
+    assert!(text.ends_with("# EOF\n"));
+}
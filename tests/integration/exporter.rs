@@ -1,4 +1,6 @@
+use opentelemetry::KeyValue;
 use opentelemetry::metrics::MeterProvider;
+use opentelemetry_openmetrics::convert::{ConversionOptions, TranslationStrategy};
 use opentelemetry_openmetrics::exporter::OpenMetricsExporter;
 use opentelemetry_sdk::metrics::SdkMeterProvider;
 
@@ -22,3 +24,94 @@ fn exporter_exports() {
     let metrics_text = rt.block_on(exporter.text());
     assert!(metrics_text.contains("# TYPE a_gauge"));
 }
+
+#[test]
+fn exporter_sync_text() {
+    let exporter = OpenMetricsExporter::default();
+    let meter_provider = SdkMeterProvider::builder()
+        .with_periodic_exporter(exporter.clone())
+        .build();
+    assert_eq!(exporter.sync_text(), String::new());
+
+    let meter = meter_provider.meter("meter.one");
+    let counter = meter.u64_counter("a_counter").build();
+    counter.add(3, &[]);
+
+    meter_provider.force_flush().unwrap();
+    let metrics_text = exporter.sync_text();
+    assert!(metrics_text.contains("a_counter_total{otel_scope_name=\"meter.one\"} 3"));
+    assert!(metrics_text.ends_with("# EOF\n"));
+}
+
+#[test]
+fn exporter_uses_options() {
+    let exporter = OpenMetricsExporter::default().with_options(
+        ConversionOptions::default()
+            .with_scope_info(false)
+            .with_timestamps(false)
+            .with_created(false),
+    );
+    let meter_provider = SdkMeterProvider::builder()
+        .with_periodic_exporter(exporter.clone())
+        .build();
+
+    let meter = meter_provider.meter("meter.one");
+    meter.u64_counter("a_counter").build().add(3, &[]);
+    meter_provider.force_flush().unwrap();
+    let metrics_text = exporter.sync_text();
+    assert!(metrics_text.contains("a_counter_total{} 3\n"));
+    assert!(!metrics_text.contains("_created"));
+}
+
+#[test]
+fn exporter_escapes_utf8_names() {
+    let exporter = OpenMetricsExporter::default().with_options(
+        ConversionOptions::default()
+            .with_scope_info(false)
+            .with_timestamps(false)
+            .with_translation_strategy(TranslationStrategy::NoUtf8EscapingWithSuffixes),
+    );
+    let meter_provider = SdkMeterProvider::builder()
+        .with_periodic_exporter(exporter.clone())
+        .build();
+
+    let meter = meter_provider.meter("meter.one");
+    meter
+        .u64_counter("http.requests")
+        .build()
+        .add(3, &[KeyValue::new("http.method", "GET")]);
+    meter_provider.force_flush().unwrap();
+    let metrics_text = exporter.sync_text();
+    assert!(metrics_text.contains("http_requests_total{http_method=\"GET\"} 3\n"));
+    assert!(!metrics_text.contains("\"http."));
+}
+
+#[test]
+fn exporter_snapshot() {
+    let exporter = OpenMetricsExporter::default();
+    let meter_provider = SdkMeterProvider::builder()
+        .with_periodic_exporter(exporter.clone())
+        .build();
+
+    let meter = meter_provider.meter("meter.one");
+    let gauge = meter.f64_gauge("a_gauge").build();
+    gauge.record(42.0, &[]);
+    meter_provider.force_flush().unwrap();
+    let first = exporter.snapshot();
+    assert!(first.contains("a_gauge{otel_scope_name=\"meter.one\"} 42"));
+
+    // Snapshots are shared until the next export
+    assert!(std::sync::Arc::ptr_eq(&first, &exporter.snapshot()));
+
+    gauge.record(43.0, &[]);
+    meter_provider.force_flush().unwrap();
+    assert!(first.contains("a_gauge{otel_scope_name=\"meter.one\"} 42"));
+    assert!(
+        exporter
+            .snapshot()
+            .contains("a_gauge{otel_scope_name=\"meter.one\"} 43")
+    );
+
+    #[cfg(feature = "bytes")]
+    assert_eq!(exporter.snapshot_bytes(), exporter.snapshot().as_bytes());
+}
@@ -0,0 +1,163 @@
+use opentelemetry::KeyValue;
+use opentelemetry::metrics::MeterProvider;
+use opentelemetry_openmetrics::convert::{ConversionError, ConversionOptions, WriteOpenMetrics};
+use opentelemetry_sdk::metrics::SdkMeterProvider;
+use opentelemetry_sdk::metrics::data::ResourceMetrics;
+use opentelemetry_sdk::metrics::reader::MetricReader;
+use testsupport::reader::TestMetricsReader;
+
+#[test]
+fn families_are_merged_across_scopes() {
+    let reader = TestMetricsReader::default();
+    let meter_provider = SdkMeterProvider::builder()
+        .with_reader(reader.clone())
+        .build();
+    for (scope, value) in [("lib.b", 2.5), ("lib.a", 1.5)] {
+        meter_provider
+            .meter(scope)
+            .f64_histogram("http.client.duration")
+            .with_unit("s")
+            .build()
+            .record(value, &[KeyValue::new("kk", "v1")]);
+    }
+    let mut metrics = ResourceMetrics::default();
+    reader.collect(&mut metrics).unwrap();
+
+    let options = ConversionOptions::default()
+        .with_scope_info(true)
+        .with_timestamps(false);
+    let mut text = String::new();
+    metrics
+        .write_as_openmetrics_with(&options, &mut text)
+        .unwrap();
+
+    assert_eq!(
+        text.matches("# TYPE http_client_duration_seconds histogram\n")
+            .count(),
+        1
+    );
+    let lib_a = text
+        .find("http_client_duration_seconds_count{kk=\"v1\",otel_scope_name=\"lib.a\"} 1\n")
+        .unwrap();
+    let lib_b = text
+        .find("http_client_duration_seconds_count{kk=\"v1\",otel_scope_name=\"lib.b\"} 1\n")
+        .unwrap();
+    assert!(lib_a < lib_b);
+}
+
+#[test]
+fn merged_families_are_labeled_without_scope_info() {
+    let reader = TestMetricsReader::default();
+    let meter_provider = SdkMeterProvider::builder()
+        .with_reader(reader.clone())
+        .build();
+    for scope in ["lib.a", "lib.b"] {
+        meter_provider
+            .meter(scope)
+            .u64_counter("jobs")
+            .build()
+            .add(1, &[KeyValue::new("k", "v")]);
+    }
+    meter_provider
+        .meter("lib.a")
+        .u64_counter("tasks")
+        .build()
+        .add(1, &[KeyValue::new("k", "v")]);
+    let mut metrics = ResourceMetrics::default();
+    reader.collect(&mut metrics).unwrap();
+
+    let options = ConversionOptions::default()
+        .with_scope_info(false)
+        .with_timestamps(false);
+    let mut text = String::new();
+    metrics
+        .write_as_openmetrics_with(&options, &mut text)
+        .unwrap();
+
+    assert_eq!(text.matches("# TYPE jobs counter\n").count(), 1);
+    assert!(text.contains("jobs_total{k=\"v\",otel_scope_name=\"lib.a\"} 1\n"));
+    assert!(text.contains("jobs_total{k=\"v\",otel_scope_name=\"lib.b\"} 1\n"));
+    // families of a single scope stay unlabeled
+    assert!(text.contains("tasks_total{k=\"v\"} 1\n"));
+}
+
+#[test]
+fn conflicting_families_are_skipped() {
+    let reader = TestMetricsReader::default();
+    let meter_provider = SdkMeterProvider::builder()
+        .with_reader(reader.clone())
+        .build();
+    meter_provider
+        .meter("lib.a")
+        .u64_counter("jobs")
+        .build()
+        .add(1, &[]);
+    meter_provider
+        .meter("lib.b")
+        .f64_gauge("jobs")
+        .build()
+        .record(2.0, &[]);
+    let mut metrics = ResourceMetrics::default();
+    reader.collect(&mut metrics).unwrap();
+
+    let err = metrics.to_openmetrics_string().unwrap_err();
+    assert!(matches!(
+        err,
+        ConversionError::ConflictingFamily {
+            ref scope,
+            ref metric,
+            ref family,
+        } if scope == "lib.b" && metric == "jobs" && family == "jobs"
+    ));
+
+    let mut text = String::new();
+    let report = metrics
+        .write_as_openmetrics_with_report(
+            &ConversionOptions::default().with_lenient(true),
+            &mut text,
+        )
+        .unwrap();
+    assert!(text.contains("# TYPE jobs counter\n"));
+    assert!(!text.contains("# TYPE jobs gauge\n"));
+    assert_eq!(report.dropped.len(), 1);
+    assert_eq!(report.dropped[0].scope, "lib.b");
+}
+
+#[test]
+fn colliding_metrics_of_a_scope_are_skipped() {
+    let reader = TestMetricsReader::default();
+    let meter_provider = SdkMeterProvider::builder()
+        .with_reader(reader.clone())
+        .build();
+    let meter = meter_provider.meter("lib.a");
+    meter.u64_counter("http_requests").build().add(2, &[]);
+    meter.u64_counter("http.requests").build().add(1, &[]);
+    let mut metrics = ResourceMetrics::default();
+    reader.collect(&mut metrics).unwrap();
+
+    let err = metrics.to_openmetrics_string().unwrap_err();
+    assert!(matches!(
+        err,
+        ConversionError::ConflictingFamily {
+            ref scope,
+            ref metric,
+            ref family,
+        } if scope == "lib.a" && metric == "http_requests" && family == "http_requests"
+    ));
+
+    let mut text = String::new();
+    let report = metrics
+        .write_as_openmetrics_with_report(
+            &ConversionOptions::default()
+                .with_lenient(true)
+                .with_timestamps(false),
+            &mut text,
+        )
+        .unwrap();
+    assert_eq!(text.matches("# TYPE http_requests counter\n").count(), 1);
+    assert_eq!(text.matches("http_requests_total{").count(), 1);
+    assert!(text.contains("http_requests_total{otel_scope_name=\"lib.a\"} 1\n"));
+    assert_eq!(report.dropped.len(), 1);
+    assert_eq!(report.dropped[0].scope, "lib.a");
+    assert_eq!(report.dropped[0].metric, "http_requests");
+}
@@ -1,6 +1,20 @@
+#[cfg(feature = "actix")]
+mod actix;
+mod delta;
 #[cfg(feature = "exporter")]
 mod exporter;
+mod families;
 mod parsing;
+#[cfg(feature = "reader")]
+mod reader;
+mod report;
+#[cfg(feature = "server")]
+mod server;
 #[cfg(feature = "otel_scope_info")]
 // Changes attributes
 mod snapshot;
+#[cfg(feature = "tokio")]
+mod stream;
+#[cfg(feature = "tower")]
+mod tower;
+mod translation;
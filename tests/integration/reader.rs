@@ -0,0 +1,55 @@
+use opentelemetry::metrics::MeterProvider;
+use opentelemetry_openmetrics::convert::ConversionOptions;
+use opentelemetry_openmetrics::reader::OpenMetricsReader;
+use opentelemetry_sdk::metrics::{SdkMeterProvider, Temporality};
+
+#[test]
+fn reader_collects_on_text() {
+    let reader = OpenMetricsReader::default();
+    let meter_provider = SdkMeterProvider::builder()
+        .with_reader(reader.clone())
+        .build();
+
+    let meter = meter_provider.meter("meter.one");
+    let gauge = meter.f64_gauge("a_gauge").build();
+    gauge.record(42.0, &[]);
+    let metrics_text = reader.text().unwrap();
+    assert!(metrics_text.contains("a_gauge{otel_scope_name=\"meter.one\"} 42"));
+
+    gauge.record(43.0, &[]);
+    let metrics_text = reader.text().unwrap();
+    assert!(metrics_text.contains("a_gauge{otel_scope_name=\"meter.one\"} 43"));
+}
+
+#[test]
+fn reader_accumulates_delta() {
+    let reader = OpenMetricsReader::default().with_temporality(Temporality::Delta);
+    let meter_provider = SdkMeterProvider::builder()
+        .with_reader(reader.clone())
+        .build();
+
+    let meter = meter_provider.meter("meter.one");
+    let counter = meter.u64_counter("a_counter").build();
+    counter.add(2, &[]);
+    reader.text().unwrap();
+    counter.add(3, &[]);
+    let metrics_text = reader.text().unwrap();
+    assert!(metrics_text.contains("a_counter_total{otel_scope_name=\"meter.one\"} 5"));
+}
+
+#[test]
+fn reader_uses_options() {
+    let reader = OpenMetricsReader::default().with_options(
+        ConversionOptions::default()
+            .with_scope_info(false)
+            .with_timestamps(false),
+    );
+    let meter_provider = SdkMeterProvider::builder()
+        .with_reader(reader.clone())
+        .build();
+
+    let meter = meter_provider.meter("meter.one");
+    meter.f64_gauge("a_gauge").build().record(42.0, &[]);
+    let metrics_text = reader.text().unwrap();
+    assert!(metrics_text.contains("a_gauge{} 42\n"));
+}
@@ -0,0 +1,105 @@
+use opentelemetry::KeyValue;
+use opentelemetry::metrics::MeterProvider;
+use opentelemetry_openmetrics::convert::{
+    ConversionError, ConversionOptions, RenamedMetric, SanitizedLabel, TranslationStrategy,
+    WriteOpenMetrics,
+};
+use opentelemetry_sdk::metrics::data::ResourceMetrics;
+use opentelemetry_sdk::metrics::reader::MetricReader;
+use opentelemetry_sdk::metrics::{SdkMeterProvider, Temporality};
+use testsupport::reader::TestMetricsReader;
+
+#[test]
+fn report_lists_altered_metrics() {
+    let reader = TestMetricsReader::default();
+    let meter_provider = SdkMeterProvider::builder()
+        .with_reader(reader.clone())
+        .build();
+    let meter = meter_provider.meter("meter.report");
+    meter
+        .u64_counter("http.requests")
+        .with_unit("{request}")
+        .build()
+        .add(1, &[KeyValue::new("http.method", "GET")]);
+    meter
+        .f64_gauge("temperature")
+        .with_unit("Cel")
+        .build()
+        .record(21.5, &[KeyValue::new("room", "kitchen")]);
+
+    let mut metrics = ResourceMetrics::default();
+    reader.collect(&mut metrics).unwrap();
+
+    let mut text = String::new();
+    let report = metrics
+        .write_as_openmetrics_with_report(&ConversionOptions::default(), &mut text)
+        .unwrap();
+
+    assert!(!report.is_empty());
+    assert!(report.dropped.is_empty());
+    assert_eq!(
+        report.renamed,
+        vec![
+            RenamedMetric {
+                scope: "meter.report".to_owned(),
+                original: "http.requests".to_owned(),
+                name: "http_requests".to_owned(),
+            },
+            RenamedMetric {
+                scope: "meter.report".to_owned(),
+                original: "temperature".to_owned(),
+                name: "temperature_celsius".to_owned(),
+            },
+        ]
+    );
+    assert_eq!(report.dropped_units.len(), 1);
+    assert_eq!(report.dropped_units[0].metric, "http.requests");
+    assert_eq!(report.dropped_units[0].unit, "{request}");
+    assert_eq!(
+        report.sanitized_labels,
+        vec![SanitizedLabel {
+            metric: "http_requests".to_owned(),
+            original: "http.method".to_owned(),
+            label: "http_method".to_owned(),
+        }]
+    );
+
+    // Units are not reported as dropped if suffixes are disabled on purpose
+    for options in [
+        ConversionOptions::default().with_unit_suffixes(false),
+        ConversionOptions::default().with_translation_strategy(TranslationStrategy::NoTranslation),
+    ] {
+        let report = metrics
+            .write_as_openmetrics_with_report(&options, &mut String::new())
+            .unwrap();
+        assert!(report.dropped_units.is_empty());
+    }
+}
+
+#[test]
+fn report_lists_dropped_metrics() {
+    let reader = TestMetricsReader::with_temporality(Temporality::Delta);
+    let meter_provider = SdkMeterProvider::builder()
+        .with_reader(reader.clone())
+        .build();
+    let meter = meter_provider.meter("meter.report");
+    meter.u64_counter("jobs").build().add(1, &[]);
+
+    let mut metrics = ResourceMetrics::default();
+    reader.collect(&mut metrics).unwrap();
+
+    let mut text = String::new();
+    let report = metrics
+        .write_as_openmetrics_with_report(
+            &ConversionOptions::default().with_lenient(true),
+            &mut text,
+        )
+        .unwrap();
+
+    assert_eq!(report.dropped.len(), 1);
+    assert_eq!(report.dropped[0].metric, "jobs");
+    assert!(matches!(
+        report.dropped[0].reason,
+        ConversionError::UnsupportedTemporality { .. }
+    ));
+}
@@ -0,0 +1,82 @@
+use std::io::{Read, Write};
+use std::net::{Ipv4Addr, SocketAddr, TcpStream};
+use std::time::{Duration, Instant};
+
+use opentelemetry::metrics::MeterProvider;
+use opentelemetry_openmetrics::convert::MIME_TYPE;
+use opentelemetry_openmetrics::exporter::OpenMetricsExporter;
+use opentelemetry_sdk::metrics::SdkMeterProvider;
+
+fn request(addr: SocketAddr, request: &str) -> String {
+    let mut stream = TcpStream::connect(addr).unwrap();
+    stream.write_all(request.as_bytes()).unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    response
+}
+
+#[test]
+fn server_serves_metrics() {
+    let exporter = OpenMetricsExporter::default();
+    let meter_provider = SdkMeterProvider::builder()
+        .with_periodic_exporter(exporter.clone())
+        .build();
+    let server = exporter
+        .server()
+        .with_address((Ipv4Addr::LOCALHOST, 0))
+        .with_path("/custom")
+        .start()
+        .unwrap();
+    let addr = server.local_addr();
+
+    let meter = meter_provider.meter("meter.one");
+    let gauge = meter.f64_gauge("a_gauge").build();
+    gauge.record(42.0, &[]);
+    meter_provider.force_flush().unwrap();
+
+    let response = request(addr, "GET /custom?x=1 HTTP/1.1\r\nHost: localhost\r\n\r\n");
+    let (head, body) = response.split_once("\r\n\r\n").unwrap();
+    assert!(head.starts_with("HTTP/1.1 200 OK\r\n"));
+    assert!(head.contains(&format!("Content-Type: {MIME_TYPE}\r\n")));
+    assert!(head.contains(&format!("Content-Length: {}\r\n", body.len())));
+    assert!(body.contains("a_gauge{otel_scope_name=\"meter.one\"} 42"));
+    assert!(body.ends_with("# EOF\n"));
+
+    let response = request(addr, "HEAD /custom HTTP/1.1\r\n\r\n");
+    assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+    assert!(response.ends_with("\r\n\r\n"));
+
+    let response = request(addr, "GET /metrics HTTP/1.1\r\n\r\n");
+    assert!(response.starts_with("HTTP/1.1 404 Not Found\r\n"));
+
+    let response = request(addr, "POST /custom HTTP/1.1\r\n\r\n");
+    assert!(response.starts_with("HTTP/1.1 405 Method Not Allowed\r\n"));
+    assert!(response.contains("Allow: GET, HEAD\r\n"));
+
+    let response = request(addr, "garbage\r\n\r\n");
+    assert!(response.starts_with("HTTP/1.1 400 Bad Request\r\n"));
+
+    meter_provider.shutdown().unwrap();
+    assert!(TcpStream::connect(addr).is_err());
+}
+
+#[test]
+fn server_serves_while_connections_are_idle() {
+    let exporter = OpenMetricsExporter::default();
+    let server = exporter
+        .server()
+        .with_address((Ipv4Addr::LOCALHOST, 0))
+        .start()
+        .unwrap();
+    let addr = server.local_addr();
+
+    // as many connections as the server has workers
+    let idle: Vec<_> = (0..4).map(|_| TcpStream::connect(addr).unwrap()).collect();
+    let start = Instant::now();
+    let response = request(addr, "GET /metrics HTTP/1.1\r\n\r\n");
+    assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+    assert!(start.elapsed() < Duration::from_secs(5));
+    drop(idle);
+
+    server.shutdown(Duration::from_secs(5)).unwrap();
+}
@@ -17,7 +17,7 @@ fn matches_snapshot() {
             .unwrap()
             .as_secs_f64()
             .to_string();
-        formatted = formatted.replace(&format!("{ts}"), &format!("<TIMESTAMP_{}>", i));
+        formatted = formatted.replace(&ts, &format!("<TIMESTAMP_{}>", i));
     }
     assert_snapshot!(formatted);
 }
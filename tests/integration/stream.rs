@@ -0,0 +1,72 @@
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use opentelemetry_openmetrics::convert::{
+    ConversionOptions, WriteOpenMetrics, write_as_openmetrics_async,
+};
+use testsupport::resource_metrics::make_test_metrics;
+use tokio::io::AsyncWrite;
+
+/// Records the size of every write.
+#[derive(Default)]
+struct Recorder {
+    data: Vec<u8>,
+    writes: Vec<usize>,
+}
+
+impl AsyncWrite for Recorder {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        self.data.extend_from_slice(buf);
+        self.writes.push(buf.len());
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+#[test]
+fn async_writer_streams_chunks() {
+    let metrics = make_test_metrics();
+    let options = ConversionOptions::default();
+    let rt = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap();
+
+    let mut recorder = Recorder::default();
+    rt.block_on(write_as_openmetrics_async(
+        &metrics,
+        &options,
+        64,
+        &mut recorder,
+    ))
+    .unwrap();
+
+    assert_eq!(
+        String::from_utf8(recorder.data).unwrap(),
+        metrics.to_openmetrics_string().unwrap()
+    );
+    assert!(recorder.writes.len() > 1);
+
+    let mut output = Vec::new();
+    rt.block_on(write_as_openmetrics_async(
+        &metrics,
+        &options,
+        1 << 20,
+        &mut output,
+    ))
+    .unwrap();
+    assert_eq!(
+        String::from_utf8(output).unwrap(),
+        metrics.to_openmetrics_string().unwrap()
+    );
+}
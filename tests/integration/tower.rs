@@ -0,0 +1,130 @@
+use std::io::Read;
+
+use http::header::{ACCEPT_ENCODING, CONTENT_ENCODING, CONTENT_TYPE};
+use http::{Request, Response, StatusCode};
+use http_body_util::{BodyExt, Full};
+use opentelemetry::metrics::MeterProvider;
+use opentelemetry_openmetrics::convert::MIME_TYPE;
+use opentelemetry_openmetrics::exporter::OpenMetricsExporter;
+use opentelemetry_openmetrics::tower::MetricsService;
+use opentelemetry_sdk::metrics::SdkMeterProvider;
+use tower_service::Service;
+
+fn call(service: &mut MetricsService, request: Request<()>) -> (Response<()>, Vec<u8>) {
+    let rt = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap();
+    let response: Response<Full<bytes::Bytes>> = rt.block_on(service.call(request)).unwrap();
+    let (parts, body) = response.into_parts();
+    let body = rt.block_on(body.collect()).unwrap().to_bytes().to_vec();
+    (Response::from_parts(parts, ()), body)
+}
+
+#[test]
+fn service_serves_metrics() {
+    let exporter = OpenMetricsExporter::default();
+    let meter_provider = SdkMeterProvider::builder()
+        .with_periodic_exporter(exporter.clone())
+        .build();
+    let meter = meter_provider.meter("meter.one");
+    let gauge = meter.f64_gauge("a_gauge").build();
+    gauge.record(42.0, &[]);
+    meter_provider.force_flush().unwrap();
+
+    let mut service = MetricsService::new(exporter.clone());
+
+    let (response, body) = call(&mut service, Request::get("/metrics").body(()).unwrap());
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()[CONTENT_TYPE], MIME_TYPE);
+    assert!(response.headers().get(CONTENT_ENCODING).is_none());
+    assert_eq!(body, exporter.snapshot().as_bytes());
+
+    let (response, body) = call(
+        &mut service,
+        Request::get("/metrics")
+            .header(ACCEPT_ENCODING, "br;q=1.0, gzip;q=0.5, identity;q=0.1")
+            .body(())
+            .unwrap(),
+    );
+    assert_eq!(response.headers()[CONTENT_ENCODING], "gzip");
+    let mut text = String::new();
+    flate2::read::GzDecoder::new(body.as_slice())
+        .read_to_string(&mut text)
+        .unwrap();
+    assert_eq!(text, *exporter.snapshot());
+
+    let (response, _) = call(
+        &mut service,
+        Request::get("/metrics")
+            .header(ACCEPT_ENCODING, "gzip;q=0")
+            .body(())
+            .unwrap(),
+    );
+    assert!(response.headers().get(CONTENT_ENCODING).is_none());
+
+    let (response, _) = call(&mut service, Request::post("/metrics").body(()).unwrap());
+    assert_eq!(response.status(), StatusCode::METHOD_NOT_ALLOWED);
+}
+
+#[cfg(feature = "axum")]
+#[test]
+fn axum_handler_serves_metrics() {
+    use axum::routing::get;
+
+    let exporter = OpenMetricsExporter::default();
+    let mut router = axum::Router::new()
+        .route("/metrics", get(opentelemetry_openmetrics::tower::handler))
+        .route_service("/service", MetricsService::new(exporter.clone()))
+        .with_state(exporter);
+
+    let rt = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap();
+    for path in ["/metrics", "/service"] {
+        let request = Request::get(path).body(axum::body::Body::empty()).unwrap();
+        let response = rt.block_on(router.call(request)).unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[CONTENT_TYPE], MIME_TYPE);
+    }
+}
+
+#[test]
+fn service_serves_cached_compression() {
+    let exporter = OpenMetricsExporter::default().with_gzip(true);
+    #[cfg(feature = "zstd")]
+    let exporter = exporter.with_zstd(true);
+    let meter_provider = SdkMeterProvider::builder()
+        .with_periodic_exporter(exporter.clone())
+        .build();
+    let meter = meter_provider.meter("meter.one");
+    let gauge = meter.f64_gauge("a_gauge").build();
+    gauge.record(42.0, &[]);
+    meter_provider.force_flush().unwrap();
+
+    let cached = exporter.snapshot_gzip().unwrap();
+    let mut service = MetricsService::new(exporter.clone());
+    let (response, body) = call(
+        &mut service,
+        Request::get("/metrics")
+            .header(ACCEPT_ENCODING, "gzip")
+            .body(())
+            .unwrap(),
+    );
+    assert_eq!(response.headers()[CONTENT_ENCODING], "gzip");
+    assert_eq!(body, cached);
+
+    #[cfg(feature = "zstd")]
+    {
+        let (response, body) = call(
+            &mut service,
+            Request::get("/metrics")
+                .header(ACCEPT_ENCODING, "gzip, zstd")
+                .body(())
+                .unwrap(),
+        );
+        assert_eq!(response.headers()[CONTENT_ENCODING], "zstd");
+        assert_eq!(body, exporter.snapshot_zstd().unwrap());
+        let text = zstd::decode_all(body.as_slice()).unwrap();
+        assert_eq!(text, exporter.snapshot().as_bytes());
+    }
+}
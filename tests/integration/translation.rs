@@ -0,0 +1,188 @@
+use opentelemetry::KeyValue;
+use opentelemetry::metrics::MeterProvider;
+use opentelemetry_openmetrics::convert::{
+    ConversionOptions, TranslationStrategy, WriteOpenMetrics, WritePrometheus, negotiate,
+};
+use opentelemetry_sdk::metrics::SdkMeterProvider;
+use opentelemetry_sdk::metrics::data::ResourceMetrics;
+use opentelemetry_sdk::metrics::reader::MetricReader;
+use testsupport::reader::TestMetricsReader;
+
+fn make_metrics() -> ResourceMetrics {
+    let reader = TestMetricsReader::default();
+    let meter_provider = SdkMeterProvider::builder()
+        .with_reader(reader.clone())
+        .build();
+    let meter = meter_provider.meter("meter.translation");
+    meter
+        .u64_counter("http.server.requests")
+        .with_unit("{request}")
+        .with_description("Number of requests")
+        .build()
+        .add(1, &[KeyValue::new("http.method", "GET")]);
+    meter
+        .f64_histogram("http.server.request.duration")
+        .with_unit("s")
+        .with_boundaries(vec![0.5])
+        .build()
+        .record(0.25, &[]);
+
+    let mut metrics = ResourceMetrics::default();
+    reader.collect(&mut metrics).unwrap();
+    metrics
+}
+
+fn options(strategy: TranslationStrategy) -> ConversionOptions {
+    ConversionOptions::default()
+        .with_scope_info(false)
+        .with_target_info(false)
+        .with_timestamps(false)
+        .with_translation_strategy(strategy)
+}
+
+#[test]
+fn utf8_names_are_quoted() {
+    let mut text = String::new();
+    make_metrics()
+        .write_as_openmetrics_with(
+            &options(TranslationStrategy::NoUtf8EscapingWithSuffixes),
+            &mut text,
+        )
+        .unwrap();
+
+    assert!(text.contains("# TYPE \"http.server.requests\" counter\n"));
+    assert!(text.contains("# HELP \"http.server.requests\" Number of requests\n"));
+    assert!(text.contains("{\"http.server.requests_total\",\"http.method\"=\"GET\"} 1\n"));
+    assert!(text.contains("# UNIT \"http.server.request.duration_seconds\" seconds\n"));
+    assert!(text.contains("{\"http.server.request.duration_seconds_count\"} 1\n"));
+    assert!(text.contains("{\"http.server.request.duration_seconds_bucket\",le=\"0.5\"} 1\n"));
+}
+
+#[test]
+fn untranslated_names_have_no_suffixes() {
+    let mut text = String::new();
+    make_metrics()
+        .write_as_openmetrics_with(&options(TranslationStrategy::NoTranslation), &mut text)
+        .unwrap();
+
+    assert!(text.contains("{\"http.server.requests\",\"http.method\"=\"GET\"} 1\n"));
+    assert!(!text.contains("# UNIT"));
+    assert!(text.contains("{\"http.server.request.duration_count\"} 1\n"));
+}
+
+#[test]
+fn utf8_names_are_escaped_unless_accepted() {
+    let metrics = make_metrics();
+    let options = options(TranslationStrategy::NoUtf8EscapingWithSuffixes);
+
+    let format = negotiate("application/openmetrics-text;version=1.0.0;escaping=allow-utf-8");
+    assert!(format.content_type().ends_with("escaping=allow-utf-8"));
+    let mut text = String::new();
+    format.write_as(&metrics, &options, &mut text).unwrap();
+    assert!(text.contains("{\"http.server.requests_total\",\"http.method\"=\"GET\"} 1\n"));
+
+    let format = negotiate("application/openmetrics-text;version=1.0.0");
+    let mut text = String::new();
+    format.write_as(&metrics, &options, &mut text).unwrap();
+    assert!(text.contains("http_server_requests_total{http_method=\"GET\"} 1\n"));
+    assert!(!text.contains("{\""));
+}
+
+#[test]
+fn reserved_labels_are_exported() {
+    let reader = TestMetricsReader::default();
+    let meter_provider = SdkMeterProvider::builder()
+        .with_reader(reader.clone())
+        .build();
+    let meter = meter_provider.meter("meter.translation");
+    meter
+        .f64_histogram("latency")
+        .with_boundaries(vec![0.5])
+        .build()
+        .record(0.25, &[KeyValue::new("le", "x")]);
+    let mut metrics = ResourceMetrics::default();
+    reader.collect(&mut metrics).unwrap();
+
+    let mut text = String::new();
+    metrics
+        .write_as_openmetrics_with(
+            &options(TranslationStrategy::UnderscoreEscapingWithSuffixes).with_scope_info(true),
+            &mut text,
+        )
+        .unwrap();
+    assert!(text.contains(
+        "latency_bucket{exported_le=\"x\",otel_scope_name=\"meter.translation\",le=\"0.5\"} 1\n"
+    ));
+}
+
+#[test]
+fn reserved_prefix_is_escaped_in_utf8_names() {
+    let reader = TestMetricsReader::default();
+    let meter_provider = SdkMeterProvider::builder()
+        .with_reader(reader.clone())
+        .build();
+    let meter = meter_provider.meter("meter.translation");
+    meter.u64_counter("http.requests").build().add(
+        1,
+        &[
+            KeyValue::new("__name__", "evil"),
+            KeyValue::new("__scheme__", "https"),
+        ],
+    );
+    let mut metrics = ResourceMetrics::default();
+    reader.collect(&mut metrics).unwrap();
+
+    for strategy in [
+        TranslationStrategy::NoUtf8EscapingWithSuffixes,
+        TranslationStrategy::NoTranslation,
+    ] {
+        let mut text = String::new();
+        metrics
+            .write_as_openmetrics_with(&options(strategy), &mut text)
+            .unwrap();
+        assert!(text.contains(",key__name__=\"evil\",key__scheme__=\"https\"} 1\n"));
+        assert!(!text.contains(",__"));
+    }
+}
+
+#[test]
+fn suffixes_are_not_repeated() {
+    let reader = TestMetricsReader::default();
+    let meter_provider = SdkMeterProvider::builder()
+        .with_reader(reader.clone())
+        .build();
+    let meter = meter_provider.meter("meter.translation");
+    meter
+        .f64_histogram("request.duration.seconds")
+        .with_unit("s")
+        .with_boundaries(vec![0.5])
+        .build()
+        .record(0.25, &[]);
+    meter.u64_counter("jobs_total").build().add(1, &[]);
+    meter
+        .u64_counter("bytes.total.bytes")
+        .with_unit("By")
+        .build()
+        .add(2, &[]);
+    let mut metrics = ResourceMetrics::default();
+    reader.collect(&mut metrics).unwrap();
+
+    let options = options(TranslationStrategy::UnderscoreEscapingWithSuffixes);
+    let mut text = String::new();
+    metrics
+        .write_as_openmetrics_with(&options, &mut text)
+        .unwrap();
+    assert!(text.contains("# TYPE request_duration_seconds histogram\n"));
+    assert!(text.contains("request_duration_seconds_count{} 1\n"));
+    assert!(text.contains("# TYPE jobs counter\n"));
+    assert!(text.contains("jobs_total{} 1\n"));
+    assert!(text.contains("# TYPE bytes_total_bytes counter\n"));
+    assert!(text.contains("bytes_total_bytes_total{} 2\n"));
+
+    let mut text = String::new();
+    metrics
+        .write_as_prometheus_with(&options, &mut text)
+        .unwrap();
+    assert!(text.contains("# TYPE jobs_total counter\n"));
+    assert!(!text.contains("_total_total"));
+}
@@ -1,10 +1,10 @@
 use opentelemetry::KeyValue;
 use opentelemetry::metrics::MeterProvider;
-use opentelemetry_sdk::metrics::SdkMeterProvider;
 use opentelemetry_sdk::metrics::data::{
-    AggregatedMetrics, Gauge, Histogram, MetricData, ResourceMetrics, Sum,
+    AggregatedMetrics, ExponentialHistogram, Gauge, Histogram, MetricData, ResourceMetrics, Sum,
 };
 use opentelemetry_sdk::metrics::reader::MetricReader;
+use opentelemetry_sdk::metrics::{Aggregation, Instrument, SdkMeterProvider, Stream};
 
 use crate::reader::TestMetricsReader;
 
@@ -34,10 +34,10 @@ pub fn make_f64_gauge_metric(values: Vec<(f64, Vec<KeyValue>)>) -> Gauge<f64> {
     for scope in &scope_metrics {
         if scope.scope().name() == scope_name {
             for metric in scope.metrics() {
-                if metric.name() == MYGAUGE {
-                    if let AggregatedMetrics::F64(MetricData::Gauge(gauge)) = metric.data() {
-                        return gauge.clone();
-                    }
+                if metric.name() == MYGAUGE
+                    && let AggregatedMetrics::F64(MetricData::Gauge(gauge)) = metric.data()
+                {
+                    return gauge.clone();
                 }
             }
         }
@@ -91,10 +91,10 @@ pub fn make_u64_gauge_metric(values: Vec<(u64, Vec<KeyValue>)>) -> Gauge<u64> {
     for scope in &scope_metrics {
         if scope.scope().name() == scope_name {
             for metric in scope.metrics() {
-                if metric.name() == MYGAUGE {
-                    if let AggregatedMetrics::U64(MetricData::Gauge(gauge)) = metric.data() {
-                        return gauge.clone();
-                    }
+                if metric.name() == MYGAUGE
+                    && let AggregatedMetrics::U64(MetricData::Gauge(gauge)) = metric.data()
+                {
+                    return gauge.clone();
                 }
             }
         }
@@ -148,10 +148,10 @@ pub fn make_i64_gauge_metric(values: Vec<(i64, Vec<KeyValue>)>) -> Gauge<i64> {
     for scope in &scope_metrics {
         if scope.scope().name() == scope_name {
             for metric in scope.metrics() {
-                if metric.name() == MYGAUGE {
-                    if let AggregatedMetrics::I64(MetricData::Gauge(gauge)) = metric.data() {
-                        return gauge.clone();
-                    }
+                if metric.name() == MYGAUGE
+                    && let AggregatedMetrics::I64(MetricData::Gauge(gauge)) = metric.data()
+                {
+                    return gauge.clone();
                 }
             }
         }
@@ -205,10 +205,10 @@ pub fn make_u64_counter_metric(values: Vec<(u64, Vec<KeyValue>)>) -> Sum<u64> {
     for scope in &scope_metrics {
         if scope.scope().name() == scope_name {
             for metric in scope.metrics() {
-                if metric.name() == MYCOUNTER {
-                    if let AggregatedMetrics::U64(MetricData::Sum(sum)) = metric.data() {
-                        return sum.clone();
-                    }
+                if metric.name() == MYCOUNTER
+                    && let AggregatedMetrics::U64(MetricData::Sum(sum)) = metric.data()
+                {
+                    return sum.clone();
                 }
             }
         }
@@ -262,10 +262,10 @@ pub fn make_f64_counter_metric(values: Vec<(f64, Vec<KeyValue>)>) -> Sum<f64> {
     for scope in &scope_metrics {
         if scope.scope().name() == scope_name {
             for metric in scope.metrics() {
-                if metric.name() == MYCOUNTER {
-                    if let AggregatedMetrics::F64(MetricData::Sum(sum)) = metric.data() {
-                        return sum.clone();
-                    }
+                if metric.name() == MYCOUNTER
+                    && let AggregatedMetrics::F64(MetricData::Sum(sum)) = metric.data()
+                {
+                    return sum.clone();
                 }
             }
         }
@@ -319,10 +319,10 @@ pub fn make_i64_counter_metric(values: Vec<(i64, Vec<KeyValue>)>) -> Sum<i64> {
     for scope in &scope_metrics {
         if scope.scope().name() == scope_name {
             for metric in scope.metrics() {
-                if metric.name() == MYCOUNTER {
-                    if let AggregatedMetrics::I64(MetricData::Sum(sum)) = metric.data() {
-                        return sum.clone();
-                    }
+                if metric.name() == MYCOUNTER
+                    && let AggregatedMetrics::I64(MetricData::Sum(sum)) = metric.data()
+                {
+                    return sum.clone();
                 }
             }
         }
@@ -376,11 +376,10 @@ pub fn make_f64_histogram_metric(values: Vec<(f64, Vec<KeyValue>)>) -> Histogram
     for scope in &scope_metrics {
         if scope.scope().name() == scope_name {
             for metric in scope.metrics() {
-                if metric.name() == MYHISTOGRAM {
-                    if let AggregatedMetrics::F64(MetricData::Histogram(histogram)) = metric.data()
-                    {
-                        return histogram.clone();
-                    }
+                if metric.name() == MYHISTOGRAM
+                    && let AggregatedMetrics::F64(MetricData::Histogram(histogram)) = metric.data()
+                {
+                    return histogram.clone();
                 }
             }
         }
@@ -448,11 +447,10 @@ pub fn make_u64_histogram_metric(values: Vec<(u64, Vec<KeyValue>)>) -> Histogram
     for scope in &scope_metrics {
         if scope.scope().name() == scope_name {
             for metric in scope.metrics() {
-                if metric.name() == MYHISTOGRAM {
-                    if let AggregatedMetrics::U64(MetricData::Histogram(histogram)) = metric.data()
-                    {
-                        return histogram.clone();
-                    }
+                if metric.name() == MYHISTOGRAM
+                    && let AggregatedMetrics::U64(MetricData::Histogram(histogram)) = metric.data()
+                {
+                    return histogram.clone();
                 }
             }
         }
@@ -493,3 +491,73 @@ fn test_make_u64_histogram_metric() {
         values.iter().map(|v| v.0).sum()
     );
 }
+
+pub fn make_f64_exponential_histogram_metric(
+    values: Vec<(f64, Vec<KeyValue>)>,
+) -> ExponentialHistogram<f64> {
+    let reader = TestMetricsReader::default();
+    let meter_provider = SdkMeterProvider::builder()
+        .with_reader(reader.clone())
+        .with_view(|_: &Instrument| {
+            Stream::builder()
+                .with_aggregation(Aggregation::Base2ExponentialHistogram {
+                    max_size: 160,
+                    max_scale: 2,
+                    record_min_max: true,
+                })
+                .build()
+                .ok()
+        })
+        .build();
+    let scope_name = "test_meter";
+    let meter = meter_provider.meter(scope_name);
+
+    const MYHISTOGRAM: &str = "myhistogram";
+    let histogram_builder = meter.f64_histogram(MYHISTOGRAM);
+    let histogram = histogram_builder.build();
+
+    // Record all values with their attributes
+    for (value, attrs) in values {
+        histogram.record(value, attrs.as_slice());
+    }
+
+    // Collect metrics
+    let mut metrics = ResourceMetrics::default();
+    reader.collect(&mut metrics).unwrap();
+
+    // Extract the histogram data
+    let scope_metrics = metrics.scope_metrics().collect::<Vec<_>>();
+    for scope in &scope_metrics {
+        if scope.scope().name() == scope_name {
+            for metric in scope.metrics() {
+                if metric.name() == MYHISTOGRAM
+                    && let AggregatedMetrics::F64(MetricData::ExponentialHistogram(histogram)) =
+                        metric.data()
+                {
+                    return histogram.clone();
+                }
+            }
+        }
+    }
+
+    unreachable!("should have found exponential histogram data")
+}
+
+#[test]
+fn test_make_f64_exponential_histogram_metric() {
+    let values = &[(2.5, vec![KeyValue::new("key", "value")]), (-3.0, vec![])];
+    let histogram = make_f64_exponential_histogram_metric(values.to_vec());
+
+    assert_eq!(histogram.data_points().count(), values.len());
+    assert_eq!(
+        histogram
+            .data_points()
+            .map(|dp| dp.attributes().count())
+            .sum::<usize>(),
+        1
+    );
+    assert_eq!(
+        histogram.data_points().map(|dp| dp.sum()).sum::<f64>(),
+        values.iter().map(|v| v.0).sum()
+    );
+}
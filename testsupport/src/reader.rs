@@ -8,12 +8,24 @@ use opentelemetry_sdk::metrics::{InstrumentKind, ManualReader, Pipeline, Tempora
 #[derive(Debug, Clone)]
 pub struct TestMetricsReader {
     inner: Arc<ManualReader>,
+    temporality: Temporality,
 }
 
 impl Default for TestMetricsReader {
     fn default() -> Self {
+        Self::with_temporality(Temporality::Cumulative)
+    }
+}
+
+impl TestMetricsReader {
+    pub fn with_temporality(temporality: Temporality) -> Self {
         Self {
-            inner: Arc::new(ManualReader::builder().build()),
+            inner: Arc::new(
+                ManualReader::builder()
+                    .with_temporality(temporality)
+                    .build(),
+            ),
+            temporality,
         }
     }
 }
@@ -40,6 +52,6 @@ impl MetricReader for TestMetricsReader {
     }
 
     fn temporality(&self, _kind: InstrumentKind) -> Temporality {
-        Temporality::Cumulative
+        self.temporality
     }
 }
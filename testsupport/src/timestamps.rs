@@ -10,6 +10,7 @@ pub fn get_all_timestamps(metrics: &ResourceMetrics) -> Vec<SystemTime> {
             }
             MetricData::Sum(sum) => {
                 timestamps.push(sum.time());
+                timestamps.push(sum.start_time());
             }
             MetricData::Histogram(histogram) => {
                 timestamps.push(histogram.time());